@@ -0,0 +1,259 @@
+use crate::config;
+use base64::Engine;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::Command;
+
+// Credentials for a simpledash server behind an auth proxy, from the flags, `SC_TOKEN` /
+// `SC_USER` or an `auth` section in the config file. Secrets can be given as is, read from a
+// file or printed by a command (e.g `pass show simpledash`).
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    pub token: Option<String>,
+    pub token_command: Option<String>,
+    pub token_file: Option<PathBuf>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub password_command: Option<String>,
+    pub password_file: Option<PathBuf>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+// The headers sent with the `/context` request and the websocket handshake.
+#[derive(Default, Clone, PartialEq)]
+pub struct Auth {
+    pub headers: Vec<(String, String)>,
+    // where the headers came from, to get a fresh token for a reconnect
+    config: AuthConfig,
+}
+
+// the values are secrets, they never end up in a panic or a log
+impl std::fmt::Debug for Auth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = self.headers.iter().map(|(name, _)| name);
+        f.debug_list().entries(names).finish()
+    }
+}
+
+impl AuthConfig {
+    // SC_TOKEN and SC_USER (user:password), only used if the flags set no credentials
+    pub fn from_env() -> AuthConfig {
+        let mut auth = AuthConfig {
+            token: std::env::var("SC_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
+            ..Default::default()
+        };
+        if let Ok(user) = std::env::var("SC_USER") {
+            auth.set_user(&user);
+        }
+        auth
+    }
+    // user:password, or just the user if the password comes from somewhere else
+    pub fn set_user(&mut self, user: &str) {
+        let (username, password) = match user.split_once(':') {
+            Some((username, password)) => (username, Some(password.to_string())),
+            None => (user, None),
+        };
+        self.username = Some(username.to_string()).filter(|username| !username.is_empty());
+        self.password = password;
+    }
+    fn has_credentials(&self) -> bool {
+        self.token.is_some()
+            || self.token_command.is_some()
+            || self.token_file.is_some()
+            || self.username.is_some()
+    }
+    // the credentials of the first one that has any win, headers are merged with ours winning
+    pub fn or(mut self, fallback: AuthConfig) -> AuthConfig {
+        let mut headers = fallback.headers.clone();
+        headers.append(&mut self.headers);
+        let mut auth = match self.has_credentials() {
+            true => self,
+            false => fallback,
+        };
+        auth.headers = headers;
+        auth
+    }
+    pub fn resolve(&self) -> Result<Auth, String> {
+        let token = secret(&self.token, &self.token_command, &self.token_file, "token")?;
+        let mut headers = match (token, &self.username) {
+            (Some(_), Some(_)) => {
+                return Err("Error: use either a token or a username, not both".to_string())
+            }
+            (Some(token), None) => vec![("Authorization".to_string(), format!("Bearer {}", token))],
+            (None, Some(username)) => {
+                let password = secret(
+                    &self.password,
+                    &self.password_command,
+                    &self.password_file,
+                    "password",
+                )?
+                .unwrap_or_default();
+                let credentials = base64::engine::general_purpose::STANDARD
+                    .encode(format!("{}:{}", username, password));
+                vec![(
+                    "Authorization".to_string(),
+                    format!("Basic {}", credentials),
+                )]
+            }
+            (None, None) => Vec::new(),
+        };
+        for (name, value) in self.headers.iter() {
+            if !is_header_name(name) {
+                return Err(format!("Error: invalid header name '{}'", name));
+            }
+            headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+            headers.push((name.clone(), value.clone()));
+        }
+        Ok(Auth {
+            headers,
+            config: self.clone(),
+        })
+    }
+}
+
+impl Auth {
+    // runs the commands and reads the files again, tokens from a command are often short-lived
+    pub fn refresh(&mut self) -> Result<(), String> {
+        *self = self.config.resolve()?;
+        Ok(())
+    }
+}
+
+// --header "X-Team: payments"
+pub fn parse_header(header: &str) -> Option<(String, String)> {
+    let (name, value) = header.split_once(':')?;
+    let name = name.trim();
+    is_header_name(name).then(|| (name.to_string(), value.trim().to_string()))
+}
+
+fn is_header_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+}
+
+fn secret(
+    value: &Option<String>,
+    command: &Option<String>,
+    file: &Option<PathBuf>,
+    what: &str,
+) -> Result<Option<String>, String> {
+    let secret = match (value, command, file) {
+        (Some(value), None, None) => value.clone(),
+        (None, Some(command), None) => run(command, what)?,
+        (None, None, Some(file)) => std::fs::read_to_string(config::expand_home(file))
+            .map_err(|e| format!("Error reading the {} from {}: {}", what, file.display(), e))?,
+        (None, None, None) => return Ok(None),
+        _ => {
+            return Err(format!(
+                "Error: the {} can come from only one of {0}, {0}_command and {0}_file",
+                what
+            ))
+        }
+    };
+    // files and commands usually end with a newline
+    Ok(Some(secret.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+fn run(command: &str, what: &str) -> Result<String, String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output()
+        .map_err(|e| format!("Error running '{}' for the {}: {}", command, what, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Error: '{}' failed to print the {} ({}): {}",
+            command,
+            what,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|_| format!("Error: '{}' printed a {} that isn't utf-8", command, what))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_reads_secrets_from_commands_and_lets_headers_win() {
+        // Arrange
+        let token = AuthConfig {
+            token_command: Some("echo secret".to_string()),
+            headers: BTreeMap::from([("X-Team".to_string(), "payments".to_string())]),
+            ..Default::default()
+        };
+        let basic = AuthConfig {
+            username: Some("alice".to_string()),
+            password_command: Some("printf 'open sesame\\n'".to_string()),
+            headers: BTreeMap::from([("authorization".to_string(), "Custom x".to_string())]),
+            ..Default::default()
+        };
+        let failing = AuthConfig {
+            token_command: Some("exit 1".to_string()),
+            ..Default::default()
+        };
+
+        // Act
+        let token = token.resolve();
+        let basic = basic.resolve();
+        let failing = failing.resolve();
+
+        // Assert
+        assert_eq!(
+            token.unwrap().headers,
+            vec![
+                ("Authorization".to_string(), "Bearer secret".to_string()),
+                ("X-Team".to_string(), "payments".to_string()),
+            ]
+        );
+        // a header of our own replaces the basic auth
+        assert_eq!(
+            basic.unwrap().headers,
+            vec![("authorization".to_string(), "Custom x".to_string())]
+        );
+        assert!(failing.is_err());
+    }
+    #[test]
+    fn or_takes_the_credentials_from_the_first_with_any_and_merges_headers() {
+        // Arrange
+        let mut flags = AuthConfig::default();
+        flags.set_user("alice:secret");
+        flags
+            .headers
+            .insert("X-Team".to_string(), "payments".to_string());
+        let profile = AuthConfig {
+            token: Some("profile-token".to_string()),
+            headers: BTreeMap::from([
+                ("X-Team".to_string(), "platform".to_string()),
+                ("X-Env".to_string(), "prod".to_string()),
+            ]),
+            ..Default::default()
+        };
+
+        // Act
+        let auth = flags.or(profile).resolve().unwrap();
+
+        // Assert
+        assert_eq!(
+            auth.headers,
+            vec![
+                (
+                    "Authorization".to_string(),
+                    "Basic YWxpY2U6c2VjcmV0".to_string()
+                ),
+                ("X-Env".to_string(), "prod".to_string()),
+                ("X-Team".to_string(), "payments".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,65 @@
+// a fake simpledash server to develop sc without a cluster:
+// cargo run --bin sc-mock-server -- [--port 1337] [--auth 'Bearer abc'] [--cert cert.pem --key key.pem]
+//     [demo | scenario.toml | recording.jsonl]
+use simpledash_cli::mock_server::{MockServer, Scenario};
+use std::path::Path;
+use std::process;
+use std::thread;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let value = |i: usize, example: &str| {
+        args.get(i + 1)
+            .cloned()
+            .unwrap_or_else(|| exit(&format!("Error: {} needs {}", args[i], example)))
+    };
+    let mut port = "1337".to_string();
+    let mut auth = None;
+    let mut cert = None;
+    let mut key = None;
+    let mut scenario = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--port" => port = value(i, "a port"),
+            "--auth" => auth = Some(value(i, "the Authorization header (e.g 'Bearer abc')")),
+            "--cert" => cert = Some(value(i, "a pem certificate")),
+            "--key" => key = Some(value(i, "a pem pkcs8 key")),
+            arg if scenario.is_none() && !arg.starts_with('-') => {
+                scenario = Some(arg.to_string());
+                i += 1;
+                continue;
+            }
+            arg => exit(&format!("Error: unknown argument {}", arg)),
+        }
+        i += 2; // the flag and its value
+    }
+    let mut scenario = match scenario.as_deref() {
+        None | Some("demo") => Scenario::demo(),
+        Some(path) => Scenario::load(Path::new(path)).unwrap_or_else(|e| exit(&e)),
+    };
+    scenario.auth = auth.or(scenario.auth);
+    let addr = format!("127.0.0.1:{}", port);
+    let server = match (cert, key) {
+        (Some(cert), Some(key)) => {
+            let read = |path: &str| {
+                std::fs::read(path)
+                    .unwrap_or_else(|e| exit(&format!("Error reading {}: {}", path, e)))
+            };
+            MockServer::start_tls(&addr, scenario, &read(&cert), &read(&key))
+        }
+        (None, None) => MockServer::start(&addr, scenario).map_err(|e| e.into()),
+        _ => exit("Error: --cert and --key have to be given together"),
+    }
+    .unwrap_or_else(|e| exit(&format!("Error starting on port {}: {}", port, e)));
+    println!("mock simpledash server on {}", server.url());
+    println!("try: cargo run -- -h {}", server.url());
+    loop {
+        thread::park();
+    }
+}
+
+fn exit(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
@@ -1,41 +1,281 @@
-use crate::models::ClusterInfo;
-use reqwest::{self, Error};
+use crate::auth::Auth;
+use crate::proxy::{Proxy, ProxyKind};
+use crate::tls::Tls;
+use native_tls::TlsConnector;
+use std::error::Error;
+use std::io::{BufRead, BufReader, Read, Write};
 use std::net::TcpStream;
-use tungstenite::{connect, stream::MaybeTlsStream, WebSocket};
-use url::Url;
-
-pub fn get_cluster_info(host: &String) -> Result<ClusterInfo, Error> {
-    let url = format!("{}/context", host);
-    let body = reqwest::blocking::get(url)?.text()?;
-    let cluster_info: ClusterInfo = serde_json::from_str(&body).expect("Failed to parse JSON");
-    Ok(cluster_info)
-}
-
-pub fn connect_to_host(
-    host: &str,
-) -> Result<WebSocket<MaybeTlsStream<TcpStream>>, Box<dyn std::error::Error>> {
-    let mut protocol = "ws";
-    let mut actual_host = host.to_owned();
-    if host.split_at(8).0 == "https://" {
-        protocol = "wss";
-        actual_host = host.replace("https://", "");
-    } else if host.split_at(7).0 == "http://" {
-        actual_host = host.replace("http://", "");
-    }
-    let url_str = format!("{}://{}/ws", protocol, actual_host);
-    let url = match Url::parse(&url_str) {
-        Ok(url) => url,
-        Err(e) => {
-            eprintln!("Error parsing URL: {:?}", e);
-            return Err(Box::new(e));
+use tungstenite::client::IntoClientRequest;
+use tungstenite::handshake::HandshakeError;
+use tungstenite::http::{HeaderName, HeaderValue};
+use tungstenite::{client_tls_with_config, stream::MaybeTlsStream, Connector, WebSocket};
+use url::{Position, Url};
+
+// How to reach a server besides its host: the auth headers, the tls options and the proxy,
+// used for both `/context` and the websocket.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Connection {
+    pub auth: Auth,
+    pub tls: Tls,
+    pub proxy: Option<Proxy>,
+}
+
+// talks to one simpledash server, the context request and the websocket share the tls connector
+// (so both trust the same roots) and the proxy
+pub struct Client {
+    auth: Auth,
+    tls: TlsConnector,
+    proxy: Option<Proxy>,
+    http: reqwest::blocking::Client,
+}
+
+impl Client {
+    pub fn new(connection: &Connection) -> Result<Client, Box<dyn Error>> {
+        let tls = connection.tls.connector()?;
+        // the proxy was picked from the flags, config and environment already, reqwest shouldn't pick another
+        let builder = reqwest::blocking::Client::builder()
+            .use_preconfigured_tls(tls.clone())
+            .no_proxy();
+        let builder = match &connection.proxy {
+            Some(proxy) if proxy.kind == ProxyKind::Http => {
+                let mut http_proxy = reqwest::Proxy::all(format!("http://{}", proxy.address()))?;
+                if let Some((username, password)) = proxy.basic_auth() {
+                    http_proxy = http_proxy.basic_auth(username, password);
+                }
+                builder.proxy(http_proxy)
+            }
+            _ => builder,
+        };
+        Ok(Client {
+            auth: connection.auth.clone(),
+            tls,
+            proxy: connection.proxy.clone(),
+            http: builder.build()?,
+        })
+    }
+
+    // the context as the server sent it, parsed by the data source (sc record stores it as is)
+    pub fn get_context(&self, host: &Url) -> Result<String, Box<dyn Error>> {
+        let url = context_url(host);
+        if let Some(proxy) = self
+            .proxy
+            .as_ref()
+            .filter(|proxy| proxy.kind != ProxyKind::Http)
+        {
+            return self.get_through_socks(proxy, &url);
         }
-    };
-    let (socket, _) = match connect(url) {
-        Ok((socket, response)) => (socket, response),
-        Err(e) => {
-            eprintln!("Error connecting to WebSocket: {:?}", e);
-            return Err(Box::new(e));
+        let mut request = self.http.get(url);
+        for (name, value) in self.auth.headers.iter() {
+            request = request.header(name, value);
         }
+        // an auth proxy answers with a login page or a 401, which is no context either
+        Ok(request.send()?.error_for_status()?.text()?)
+    }
+
+    pub fn refresh_auth(&mut self) -> Result<(), String> {
+        self.auth.refresh()
+    }
+
+    pub fn connect_to_host(
+        &self,
+        host: &Url,
+    ) -> Result<WebSocket<MaybeTlsStream<TcpStream>>, Box<dyn Error>> {
+        // errors are reported by the caller, printing here would mess up the dashboard while reconnecting
+        let url = websocket_url(host);
+        let stream = self.open(&url)?;
+        let connector = match url.scheme() {
+            "wss" => Connector::NativeTls(self.tls.clone()),
+            _ => Connector::Plain,
+        };
+        let mut request = url.into_client_request()?;
+        for (name, value) in self.auth.headers.iter() {
+            request.headers_mut().insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+        let (socket, _) = match client_tls_with_config(request, stream, None, Some(connector)) {
+            Ok(connected) => connected,
+            Err(HandshakeError::Failure(e)) => return Err(e.into()),
+            // only happens on non-blocking sockets
+            Err(HandshakeError::Interrupted(_)) => return Err("handshake interrupted".into()),
+        };
+        Ok(socket)
+    }
+
+    // a tcp connection to the url's host, through the proxy if there is one
+    fn open(&self, url: &Url) -> Result<TcpStream, Box<dyn Error>> {
+        let port = url.port_or_known_default().ok_or("no port")?;
+        match &self.proxy {
+            Some(proxy) => {
+                let host = url.host_str().ok_or("no host")?;
+                proxy.connect(host.trim_start_matches('[').trim_end_matches(']'), port)
+            }
+            None => Ok(TcpStream::connect(&*url.socket_addrs(|| None)?)?),
+        }
+    }
+
+    // reqwest can only do socks with tokio, so through a socks proxy /context is fetched by hand
+    fn get_through_socks(&self, proxy: &Proxy, url: &Url) -> Result<String, Box<dyn Error>> {
+        let host = url.host_str().ok_or("no host")?;
+        let port = url.port_or_known_default().ok_or("no port")?;
+        let stream = proxy.connect(host.trim_start_matches('[').trim_end_matches(']'), port)?;
+        let mut request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nAccept: application/json\r\nConnection: close\r\n",
+            &url[Position::BeforePath..Position::AfterQuery],
+            &url[Position::BeforeHost..Position::AfterPort]
+        );
+        for (name, value) in self.auth.headers.iter() {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+        match url.scheme() {
+            "https" => http_get(self.tls.connect(host, stream)?, &request, url),
+            _ => http_get(stream, &request, url),
+        }
+    }
+}
+
+// the host as people write it, without the trailing slash of an empty path, for messages and as
+// the key of the remembered namespaces
+pub fn display_host(host: &Url) -> &str {
+    host.as_str().trim_end_matches('/')
+}
+
+// the endpoints are relative to the host, which can be a base path (with or without a trailing
+// slash) and carry query parameters, e.g https://tools.corp/simpledash/?team=payments
+fn endpoint(host: &Url, name: &str) -> Url {
+    let mut url = host.clone();
+    url.set_fragment(None);
+    // http(s) urls always have a path
+    if let Ok(mut path) = url.path_segments_mut() {
+        path.pop_if_empty().push(name);
+    }
+    url
+}
+
+fn context_url(host: &Url) -> Url {
+    endpoint(host, "context")
+}
+
+fn websocket_url(host: &Url) -> Url {
+    let mut url = endpoint(host, "ws");
+    let scheme = match host.scheme() {
+        "https" => "wss",
+        _ => "ws",
     };
-    Ok(socket)
+    // switching between special schemes keeps the host and port, and can't fail
+    let _ = url.set_scheme(scheme);
+    url
+}
+
+// sends a GET and reads the body of the response, all the context request needs of http/1.1
+fn http_get(
+    mut stream: impl Read + Write,
+    request: &str,
+    url: &Url,
+) -> Result<String, Box<dyn Error>> {
+    stream.write_all(request.as_bytes())?;
+    let mut response = BufReader::new(stream);
+    let mut status_line = String::new();
+    response.read_line(&mut status_line)?;
+    let mut length = None;
+    let mut chunked = false;
+    loop {
+        let mut header = String::new();
+        response.read_line(&mut header)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => length = value.trim().parse::<usize>().ok(),
+                "transfer-encoding" => chunked = value.to_ascii_lowercase().contains("chunked"),
+                _ => {}
+            }
+        }
+    }
+    match status_line.split_whitespace().nth(1) {
+        Some(status) if status.starts_with('2') => {}
+        _ => return Err(format!("{} for url ({})", status_line.trim(), url).into()),
+    }
+    let mut body = Vec::new();
+    match (chunked, length) {
+        (true, _) => loop {
+            let mut size = String::new();
+            response.read_line(&mut size)?;
+            let size = size.split(';').next().unwrap_or_default().trim();
+            let size = usize::from_str_radix(size, 16).map_err(|_| "invalid chunked response")?;
+            if size == 0 {
+                break;
+            }
+            let start = body.len();
+            body.resize(start + size, 0);
+            response.read_exact(&mut body[start..])?;
+            response.read_line(&mut String::new())?; // the line break after the chunk
+        },
+        (false, Some(length)) => {
+            body.resize(length, 0);
+            response.read_exact(&mut body)?;
+        }
+        (false, None) => {
+            response.read_to_end(&mut body)?;
+        }
+    }
+    Ok(String::from_utf8(body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_are_joined_to_the_host_with_its_base_path_port_and_query() {
+        // Arrange
+        let hosts = [
+            "https://simpledash.corp",
+            "https://simpledash.corp/",
+            "https://tools.corp/simpledash/",
+            "http://tools.corp:8080/simpledash?team=payments#dashboard",
+            "http://[::1]:1337",
+        ]
+        .map(|host| Url::parse(host).unwrap());
+
+        // Act
+        let endpoints = hosts
+            .iter()
+            .map(|host| {
+                (
+                    context_url(host).to_string(),
+                    websocket_url(host).to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // Assert
+        let expected = [
+            (
+                "https://simpledash.corp/context",
+                "wss://simpledash.corp/ws",
+            ),
+            (
+                "https://simpledash.corp/context",
+                "wss://simpledash.corp/ws",
+            ),
+            (
+                "https://tools.corp/simpledash/context",
+                "wss://tools.corp/simpledash/ws",
+            ),
+            (
+                "http://tools.corp:8080/simpledash/context?team=payments",
+                "ws://tools.corp:8080/simpledash/ws?team=payments",
+            ),
+            ("http://[::1]:1337/context", "ws://[::1]:1337/ws"),
+        ]
+        .map(|(context, ws)| (context.to_string(), ws.to_string()));
+        assert_eq!(endpoints, expected);
+        assert_eq!(display_host(&hosts[1]), "https://simpledash.corp");
+        assert_eq!(display_host(&hosts[2]), "https://tools.corp/simpledash");
+    }
 }
@@ -0,0 +1,170 @@
+use crate::auth::AuthConfig;
+use crate::health::HealthRules;
+use crate::models::Theme;
+use crate::tls::TlsConfig;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    // applies to every cluster, profiles can add their own rules on top
+    #[serde(default)]
+    pub health: HealthRules,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub host: Option<String>,
+    pub namespace: Option<String>,
+    pub theme: Option<Theme>,
+    pub deployment_logs_link: Option<String>,
+    pub endpoint_link: Option<String>,
+    pub stale_after: Option<String>,
+    pub highlight_for: Option<String>,
+    pub health: Option<HealthRules>,
+    pub auth: Option<AuthConfig>,
+    pub tls: Option<TlsConfig>,
+    pub proxy: Option<String>,
+    pub no_proxy: Option<String>,
+}
+
+impl Config {
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile, String> {
+        let Some(name) = name.or(self.default_profile.as_deref()) else {
+            return Ok(Profile::default());
+        };
+        match self.profiles.get(name) {
+            Some(profile) => Ok(profile.clone()),
+            None if self.profiles.is_empty() => Err(format!(
+                "Error: profile '{}' not found, no profiles defined",
+                name
+            )),
+            None => Err(format!(
+                "Error: profile '{}' not found, available profiles: {}",
+                name,
+                self.profiles
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )),
+        }
+    }
+}
+
+// $XDG_CONFIG_HOME/simpledash/config.toml, falling back to ~/.config/simpledash/config.toml
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("simpledash").join("config.toml"))
+}
+
+// paths in the config file (and quoted flags) aren't expanded by a shell, so ~/ is done here
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) if !home.is_empty() => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+// a missing config file is only an error if it was asked for explicitly with --config
+pub fn load(path: Option<&Path>) -> Result<Config, String> {
+    let (path, explicit) = match path {
+        Some(path) => (path.to_path_buf(), true),
+        None => match default_path() {
+            Some(path) => (path, false),
+            None => return Ok(Config::default()),
+        },
+    };
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && !explicit => {
+            return Ok(Config::default())
+        }
+        Err(e) => return Err(format!("Error reading {}: {}", path.display(), e)),
+    };
+    parse(&content).map_err(|e| format!("Error parsing {}: {}", path.display(), e))
+}
+
+pub fn parse(content: &str) -> Result<Config, toml::de::Error> {
+    toml::from_str(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_falls_back_to_default_profile() {
+        // Arrange
+        let config = parse(
+            r#"
+            default_profile = "test"
+
+            [profiles.test]
+            host = "https://simpledash-test.mycompany.com"
+
+            [profiles.prod]
+            host = "https://simpledash-prod.mycompany.com"
+            namespace = "payments"
+            theme = "light"
+            "#,
+        )
+        .unwrap();
+
+        // Act
+        let default = config.profile(None).unwrap();
+        let prod = config.profile(Some("prod")).unwrap();
+
+        // Assert
+        assert_eq!(
+            default.host.as_deref(),
+            Some("https://simpledash-test.mycompany.com")
+        );
+        assert_eq!(prod.namespace.as_deref(), Some("payments"));
+        assert_eq!(prod.theme, Some(Theme::Light));
+    }
+    #[test]
+    fn expand_home_only_expands_a_leading_tilde() {
+        // Arrange
+        let home = PathBuf::from(std::env::var_os("HOME").unwrap());
+
+        // Act
+        let expanded = expand_home(Path::new("~/.simpledash-token"));
+        let absolute = expand_home(Path::new("/etc/ssl/corp-ca.pem"));
+        let other_user = expand_home(Path::new("~alice/token"));
+
+        // Assert
+        assert_eq!(expanded, home.join(".simpledash-token"));
+        assert_eq!(absolute, PathBuf::from("/etc/ssl/corp-ca.pem"));
+        assert_eq!(other_user, PathBuf::from("~alice/token"));
+    }
+    #[test]
+    fn profile_lists_available_profiles_if_not_found() {
+        // Arrange
+        let config = parse(
+            r#"
+            [profiles.test]
+            host = "https://simpledash-test.mycompany.com"
+            "#,
+        )
+        .unwrap();
+
+        // Act
+        let result = config.profile(Some("prod"));
+
+        // Assert
+        assert_eq!(
+            result.unwrap_err(),
+            "Error: profile 'prod' not found, available profiles: test"
+        );
+    }
+}
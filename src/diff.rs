@@ -0,0 +1,382 @@
+use crate::models::{Payload, PodStatus, Pods};
+use chrono::{DateTime, Local, SecondsFormat};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// something that changed between two consecutive payloads
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub time: SystemTime,
+    pub namespace: String,
+    pub change: Change,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    PodAdded {
+        node: String,
+        pod: Pods,
+    },
+    // the pod as it was last seen, so that it can still be shown for a while
+    PodRemoved {
+        node: String,
+        pod: Pods,
+    },
+    PodStatusChanged {
+        node: String,
+        pod: Pods,
+        from: PodStatus,
+    },
+    ReplicasChanged {
+        deployment: String,
+        from: (u32, u32),
+        to: (u32, u32),
+    },
+    DeploymentAdded {
+        deployment: String,
+        replicas: (u32, u32),
+    },
+    DeploymentRemoved {
+        deployment: String,
+    },
+    IngressAdded {
+        endpoint: String,
+        ip: String,
+    },
+    IngressRemoved {
+        endpoint: String,
+        ip: String,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::PodAdded { pod, .. } => write!(f, "pod {} added ({})", pod.name, pod.status),
+            Change::PodRemoved { pod, .. } => write!(f, "pod {} removed", pod.name),
+            Change::PodStatusChanged { pod, from, .. } => {
+                write!(f, "pod {} {} → {}", pod.name, from, pod.status)
+            }
+            Change::ReplicasChanged {
+                deployment,
+                from,
+                to,
+            } => write!(
+                f,
+                "deployment {} {}/{} → {}/{}",
+                deployment, from.0, from.1, to.0, to.1
+            ),
+            Change::DeploymentAdded {
+                deployment,
+                replicas,
+            } => write!(
+                f,
+                "deployment {} added ({}/{})",
+                deployment, replicas.0, replicas.1
+            ),
+            Change::DeploymentRemoved { deployment } => {
+                write!(f, "deployment {} removed", deployment)
+            }
+            Change::IngressAdded { endpoint, ip } => {
+                write!(f, "ingress {} added ({})", endpoint, ip)
+            }
+            Change::IngressRemoved { endpoint, .. } => write!(f, "ingress {} removed", endpoint),
+        }
+    }
+}
+
+// pods are matched by namespace and name, a pod moving to another node is not a change
+pub fn diff(previous: &Payload, current: &Payload, time: SystemTime) -> Vec<Event> {
+    let (before, after) = (pods_by_name(previous), pods_by_name(current));
+    let mut changes = Vec::new();
+    for (key, (node, pod)) in after.iter() {
+        match before.get(key) {
+            None => changes.push((
+                &pod.namespace,
+                Change::PodAdded {
+                    node: (*node).clone(),
+                    pod: (*pod).clone(),
+                },
+            )),
+            Some((_, old)) if old.status != pod.status => changes.push((
+                &pod.namespace,
+                Change::PodStatusChanged {
+                    node: (*node).clone(),
+                    pod: (*pod).clone(),
+                    from: old.status.clone(),
+                },
+            )),
+            Some(_) => {}
+        }
+    }
+    for (key, (node, pod)) in before.iter() {
+        if !after.contains_key(key) {
+            changes.push((
+                &pod.namespace,
+                Change::PodRemoved {
+                    node: (*node).clone(),
+                    pod: (*pod).clone(),
+                },
+            ));
+        }
+    }
+    for deployment in current.deployments.iter() {
+        let old = previous
+            .deployments
+            .iter()
+            .find(|d| d.namespace == deployment.namespace && d.name == deployment.name);
+        let to = (deployment.ready_replicas, deployment.replicas);
+        match old.map(|old| (old.ready_replicas, old.replicas)) {
+            None => changes.push((
+                &deployment.namespace,
+                Change::DeploymentAdded {
+                    deployment: deployment.name.clone(),
+                    replicas: to,
+                },
+            )),
+            Some(from) if from != to => changes.push((
+                &deployment.namespace,
+                Change::ReplicasChanged {
+                    deployment: deployment.name.clone(),
+                    from,
+                    to,
+                },
+            )),
+            Some(_) => {}
+        }
+    }
+    for deployment in previous.deployments.iter() {
+        if !(current.deployments.iter())
+            .any(|d| d.namespace == deployment.namespace && d.name == deployment.name)
+        {
+            changes.push((
+                &deployment.namespace,
+                Change::DeploymentRemoved {
+                    deployment: deployment.name.clone(),
+                },
+            ));
+        }
+    }
+    // an ingress is its endpoint, a new ip shows up as removed and added again
+    let (before, after) = (
+        previous.ingresses.as_deref().unwrap_or_default(),
+        current.ingresses.as_deref().unwrap_or_default(),
+    );
+    for (ingresses, others, added) in [(after, before, true), (before, after, false)] {
+        for ingress in ingresses {
+            if others
+                .iter()
+                .any(|i| i.namespace == ingress.namespace && i.endpoint == ingress.endpoint)
+            {
+                continue;
+            }
+            let (endpoint, ip) = (ingress.endpoint.clone(), ingress.ip.clone());
+            let change = match added {
+                true => Change::IngressAdded { endpoint, ip },
+                false => Change::IngressRemoved { endpoint, ip },
+            };
+            changes.push((&ingress.namespace, change));
+        }
+    }
+    changes
+        .into_iter()
+        .map(|(namespace, change)| Event {
+            time,
+            namespace: namespace.clone(),
+            change,
+        })
+        .collect()
+}
+
+// appends every event as a json line, so that what sc saw can be looked at after an incident
+pub struct EventsFile {
+    path: PathBuf,
+    file: File,
+}
+
+#[derive(Serialize)]
+struct EventRecord<'a> {
+    time: String,
+    namespace: &'a str,
+    #[serde(flatten)]
+    change: &'a Change,
+    message: String,
+}
+
+impl EventsFile {
+    pub fn open(path: &Path) -> Result<EventsFile, String> {
+        let file = File::options()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Error opening events file {}: {}", path.display(), e))?;
+        Ok(EventsFile {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+    pub fn write(&mut self, events: &[Event]) -> Result<(), String> {
+        let mut lines = String::new();
+        for event in events {
+            let record = EventRecord {
+                time: DateTime::<Local>::from(event.time)
+                    .to_rfc3339_opts(SecondsFormat::Millis, false),
+                namespace: &event.namespace,
+                change: &event.change,
+                message: event.change.to_string(),
+            };
+            lines.push_str(&serde_json::to_string(&record).map_err(|e| e.to_string())?);
+            lines.push('\n');
+        }
+        // one write per update, so that a crash doesn't leave half a line behind
+        self.file
+            .write_all(lines.as_bytes())
+            .map_err(|e| format!("Error writing events file {}: {}", self.path.display(), e))
+    }
+}
+
+fn pods_by_name(payload: &Payload) -> BTreeMap<(&str, &str), (&String, &Pods)> {
+    payload
+        .nodes
+        .iter()
+        .flat_map(|(node, pods)| pods.iter().map(move |pod| (node, pod)))
+        .map(|(node, pod)| ((pod.namespace.as_str(), pod.name.as_str()), (node, pod)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Deployment, Ingress};
+
+    fn pod(name: &str, status: PodStatus) -> Pods {
+        Pods {
+            namespace: "namespace1".to_string(),
+            name: name.to_string(),
+            image: "image:1.0".to_string(),
+            status,
+        }
+    }
+
+    #[test]
+    fn diff_finds_added_removed_and_changed_pods_and_replicas() {
+        // Arrange
+        let deployment = |ready_replicas| Deployment {
+            namespace: "namespace1".to_string(),
+            name: "api".to_string(),
+            replicas: 3,
+            ready_replicas,
+        };
+        let previous = Payload {
+            nodes: [(
+                "node1".to_string(),
+                vec![
+                    pod("api-1", PodStatus::Pending),
+                    pod("api-2", PodStatus::Running),
+                    pod("api-3", PodStatus::Running),
+                ],
+            )]
+            .into(),
+            deployments: vec![deployment(2)],
+            ..Default::default()
+        };
+        let current = Payload {
+            nodes: [
+                ("node1".to_string(), vec![pod("api-1", PodStatus::Running)]),
+                // moved to another node, not a change
+                ("node2".to_string(), vec![pod("api-2", PodStatus::Running)]),
+                ("node3".to_string(), vec![pod("api-4", PodStatus::Pending)]),
+            ]
+            .into(),
+            deployments: vec![deployment(3)],
+            ..Default::default()
+        };
+
+        // Act
+        let events = diff(&previous, &current, SystemTime::UNIX_EPOCH);
+
+        // Assert
+        let changes = events
+            .iter()
+            .map(|event| event.change.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            changes,
+            vec![
+                "pod api-1 Pending → Running",
+                "pod api-4 added (Pending)",
+                "pod api-3 removed",
+                "deployment api 2/3 → 3/3",
+            ]
+        );
+        assert!(events.iter().all(|event| event.namespace == "namespace1"));
+    }
+    #[test]
+    fn events_file_appends_deployment_and_ingress_changes_as_json_lines() {
+        // Arrange
+        let path = std::env::temp_dir().join(format!("sc-events-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let ingress = |endpoint: &str| Ingress {
+            namespace: "namespace1".to_string(),
+            endpoint: endpoint.to_string(),
+            ip: "10.0.0.1".to_string(),
+        };
+        let previous = Payload {
+            deployments: vec![Deployment {
+                namespace: "namespace1".to_string(),
+                name: "old".to_string(),
+                replicas: 1,
+                ready_replicas: 1,
+            }],
+            ingresses: Some(vec![ingress("old.example.com")]),
+            ..Default::default()
+        };
+        let current = Payload {
+            deployments: vec![Deployment {
+                namespace: "namespace1".to_string(),
+                name: "new".to_string(),
+                replicas: 3,
+                ready_replicas: 0,
+            }],
+            ingresses: Some(vec![ingress("new.example.com")]),
+            ..Default::default()
+        };
+
+        // Act
+        let events = diff(&previous, &current, SystemTime::UNIX_EPOCH);
+        let mut file = EventsFile::open(&path).unwrap();
+        file.write(&events[..2]).unwrap();
+        file.write(&events[2..]).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Assert
+        let records = content
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        let messages = records
+            .iter()
+            .map(|record| record["message"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                "deployment new added (0/3)",
+                "deployment old removed",
+                "ingress new.example.com added (10.0.0.1)",
+                "ingress old.example.com removed",
+            ]
+        );
+        assert_eq!(records[0]["kind"], "deployment_added");
+        assert_eq!(records[0]["namespace"], "namespace1");
+        assert_eq!(records[2]["endpoint"], "new.example.com");
+        assert!(records[0]["time"].as_str().unwrap().starts_with("19"));
+    }
+}
@@ -0,0 +1,51 @@
+// the patterns for namespaces, pod names and statuses
+
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+// * matches any number of characters, ? exactly one
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    let text = text.chars().collect::<Vec<char>>();
+    let (mut p, mut t) = (0, 0);
+    // where the last * was and which text position it currently swallows up to
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, swallowed)) => {
+                    p = star + 1;
+                    t = swallowed + 1;
+                    backtrack = Some((star, swallowed + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_match_supports_star_and_question_mark() {
+        assert!(glob_match("backup-*", "backup-28312345-x2x4z"));
+        assert!(glob_match("*-cron-*", "payments-cron-123"));
+        assert!(glob_match("Init:*", "Init:CrashLoopBackOff"));
+        assert!(glob_match("pod-?", "pod-1"));
+        assert!(!glob_match("pod-?", "pod-12"));
+        assert!(!glob_match("backup-*", "api-backup-1"));
+        assert!(glob_match("*", ""));
+    }
+}
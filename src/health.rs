@@ -0,0 +1,147 @@
+use crate::glob::glob_match;
+use crate::models::{Health, Pods};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+// Overrides for which pods count as healthy, from the `health` sections of the config file.
+// Statuses and pod names are glob patterns (`*` and `?`), namespace rules are checked before
+// the cluster wide ones and within them healthy before warning before failing.
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct HealthRules {
+    #[serde(default)]
+    pub healthy: Vec<String>,
+    #[serde(default)]
+    pub warning: Vec<String>,
+    #[serde(default)]
+    pub failing: Vec<String>,
+    #[serde(default)]
+    pub ignore_namespaces: Vec<String>,
+    #[serde(default)]
+    pub ignore_pods: Vec<String>,
+    #[serde(default)]
+    pub namespaces: BTreeMap<String, NamespaceRules>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct NamespaceRules {
+    #[serde(default)]
+    pub healthy: Vec<String>,
+    #[serde(default)]
+    pub warning: Vec<String>,
+    #[serde(default)]
+    pub failing: Vec<String>,
+    #[serde(default)]
+    pub ignore_pods: Vec<String>,
+}
+
+impl HealthRules {
+    // None for ignored pods, they never make a cluster unhealthy
+    pub fn health(&self, pod: &Pods) -> Option<Health> {
+        if matches_any(&self.ignore_namespaces, &pod.namespace) {
+            return None;
+        }
+        let cluster = (
+            &self.ignore_pods,
+            &self.healthy,
+            &self.warning,
+            &self.failing,
+        );
+        let levels = self
+            .namespaces
+            .get(&pod.namespace)
+            .map(|ns| (&ns.ignore_pods, &ns.healthy, &ns.warning, &ns.failing))
+            .into_iter()
+            .chain([cluster]);
+        let status = pod.status.as_str();
+        for (ignore_pods, healthy, warning, failing) in levels {
+            if matches_any(ignore_pods, &pod.name) {
+                return None;
+            }
+            if matches_any(healthy, status) {
+                return Some(Health::Healthy);
+            }
+            if matches_any(warning, status) {
+                return Some(Health::Progressing);
+            }
+            if matches_any(failing, status) {
+                return Some(Health::Failing);
+            }
+        }
+        Some(pod.status.health())
+    }
+    // anything that isn't healthy (and isn't ignored) makes a cluster BAD, warning only changes the colour
+    pub fn is_unhealthy(&self, pod: &Pods) -> bool {
+        self.health(pod)
+            .is_some_and(|health| health != Health::Healthy)
+    }
+    // our rules are checked first, so a profile's rules win over the global ones
+    pub fn with_fallback(mut self, fallback: HealthRules) -> HealthRules {
+        self.healthy.extend(fallback.healthy);
+        self.warning.extend(fallback.warning);
+        self.failing.extend(fallback.failing);
+        self.ignore_namespaces.extend(fallback.ignore_namespaces);
+        self.ignore_pods.extend(fallback.ignore_pods);
+        for (namespace, fallback) in fallback.namespaces {
+            let rules = self.namespaces.entry(namespace).or_default();
+            rules.healthy.extend(fallback.healthy);
+            rules.warning.extend(fallback.warning);
+            rules.failing.extend(fallback.failing);
+            rules.ignore_pods.extend(fallback.ignore_pods);
+        }
+        self
+    }
+}
+
+fn matches_any(patterns: &[String], text: &str) -> bool {
+    patterns.iter().any(|pattern| glob_match(pattern, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PodStatus;
+
+    fn pod(namespace: &str, name: &str, status: PodStatus) -> Pods {
+        Pods {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            image: "image:1.0".to_string(),
+            status,
+        }
+    }
+
+    #[test]
+    fn namespace_rules_win_over_cluster_rules() {
+        // Arrange
+        let rules: HealthRules = toml::from_str(
+            r#"
+            healthy = ["Terminating"]
+            ignore_namespaces = ["sandbox-*"]
+            ignore_pods = ["debug-*"]
+
+            [namespaces.jobs]
+            healthy = ["Error"]
+            failing = ["Terminating"]
+            "#,
+        )
+        .unwrap();
+
+        // Act
+        let cron = rules.health(&pod("jobs", "cron-1", PodStatus::Error));
+        let terminating_job = rules.health(&pod("jobs", "cron-2", PodStatus::Terminating));
+        let terminating_api = rules.health(&pod("api", "api-1", PodStatus::Terminating));
+        let crashing_api = rules.health(&pod("api", "api-2", PodStatus::CrashLoopBackOff));
+        let sandbox = rules.health(&pod("sandbox-1", "x", PodStatus::CrashLoopBackOff));
+        let debug = rules.health(&pod("api", "debug-1", PodStatus::CrashLoopBackOff));
+
+        // Assert
+        assert_eq!(cron, Some(Health::Healthy));
+        assert_eq!(terminating_job, Some(Health::Failing));
+        assert_eq!(terminating_api, Some(Health::Healthy));
+        assert_eq!(crashing_api, Some(Health::Failing));
+        assert_eq!(sandbox, None);
+        assert_eq!(debug, None);
+    }
+}
@@ -0,0 +1,17 @@
+pub mod auth;
+pub mod cli;
+pub mod client;
+pub mod config;
+pub mod diff;
+pub mod duration;
+pub mod glob;
+pub mod health;
+pub mod mock_server;
+pub mod models;
+pub mod proxy;
+pub mod session;
+pub mod source;
+pub mod state;
+pub mod stream;
+pub mod tls;
+pub mod tui;
@@ -1,104 +1,630 @@
-mod cli;
-mod client;
-mod models;
 use cli::clear_screen;
-use cli_table::{format::Justify, Cell, Style, Table};
+use cli_table::{format::Justify, Cell, CellStruct, Style, Table};
+use client::{display_host, Connection};
 use colored::*;
 use dialoguer::{theme::ColorfulTheme, Select};
-use models::{ClusterInfo, Payload, Pods};
-use std::{collections::HashMap, net::TcpStream};
-use tungstenite::{stream::MaybeTlsStream, WebSocket};
+use health::HealthRules;
+use models::{Cluster, ClusterInfo, Command, OutputFormat, Payload, Settings, Snapshot, Theme};
+use simpledash_cli::{
+    cli, client, diff, glob, health, models, session, source, state, stream, tui,
+};
+use source::{DataSource, LiveSource, RecordingSource};
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime};
+use std::{collections::HashMap, thread};
+use stream::{ConnectionState, ReconnectPolicy, StreamEvent};
+use tui::{Dashboard, Input, Tui};
+use url::Url;
 
 fn main() {
     let settings = cli::parse_args();
-    let mut socket = client::connect_to_host(&settings.host).expect("Error connecting to host");
-    let cluster_info =
-        client::get_cluster_info(&settings.host).expect("Failed to fetch Simpledash Context");
+    if settings.theme == Theme::Mono {
+        colored::control::set_override(false);
+    }
+    let insecure = match &settings.command {
+        Command::Overview { clusters } => clusters
+            .iter()
+            .any(|cluster| cluster.connection.tls.insecure),
+        _ => settings.connection.tls.insecure,
+    };
+    if insecure {
+        eprintln!(
+            "{}",
+            "Warning: --insecure, the server's certificate is not checked. anyone in between can read and change what sc shows"
+                .yellow()
+                .bold()
+        );
+    }
+    match settings.command {
+        Command::Dashboard => run_dashboard(&settings),
+        Command::Status { once, output } => run_status(&settings, once, output),
+        Command::Check => run_check(&settings),
+        Command::Overview { ref clusters } => run_overview(&settings, clusters),
+        Command::Record { ref out } => run_record(&settings, out),
+        Command::Replay { ref session, speed } => run_replay(&settings, session, speed),
+        Command::Wait {
+            ref deployment,
+            timeout,
+        } => run_wait(&settings, deployment, timeout),
+    }
+}
 
-    clear_screen();
-    let peek_payload = visualize_cluster_status(&mut socket, &settings.host);
-    let ns = select_namespace(&cluster_info);
-    visualize_payload(peek_payload, &ns, &cluster_info);
+// the connection options were checked when parsing the arguments, so this hardly ever fails
+fn live_source(host: &Url, connection: &Connection) -> LiveSource {
+    LiveSource::new(host, connection).unwrap_or_else(|e| {
+        cli::exit_with_error(&format!(
+            "Error setting up the connection to {}: {}",
+            display_host(host),
+            e
+        ))
+    })
+}
+
+// only sc replay has no host, cli::parse makes sure of that
+fn live_host(settings: &Settings) -> &Url {
+    settings.host.as_ref().expect("a host for a live command")
+}
+
+const EXIT_UNHEALTHY: i32 = 2;
+const EXIT_UNREACHABLE: i32 = 3;
+
+fn run_dashboard(settings: &Settings) {
+    let unreachable = |message: &str, e: Box<dyn std::error::Error>| -> ! {
+        cli::exit_with_code(&format!("{}: {}", message, e), EXIT_UNREACHABLE)
+    };
+    let host = live_host(settings);
+    let mut source = live_source(host, &settings.connection);
+    source
+        .connect()
+        .unwrap_or_else(|e| unreachable("Error connecting to host", e));
+    let cluster_info = source
+        .cluster_info()
+        .unwrap_or_else(|e| unreachable("Failed to fetch Simpledash Context", e));
+
+    // the source keeps streaming in the background so that switching namespace never reconnects
+    let events =
+        stream::spawn_payload_receiver(source, ReconnectPolicy::new(settings.max_reconnect));
+    show_dashboard(settings, display_host(host), true, cluster_info, events);
+}
+
+fn run_replay(settings: &Settings, path: &Path, speed: f64) {
+    let mut source =
+        RecordingSource::load(path, speed).unwrap_or_else(|e| cli::exit_with_error(&e));
+    let cluster_info = source.cluster_info().unwrap_or_else(|e| {
+        cli::exit_with_error(&format!(
+            "Error: invalid context in {}: {}",
+            path.display(),
+            e
+        ))
+    });
+    let host = source.host.clone();
+    let events = stream::spawn_payload_receiver(source, ReconnectPolicy::new(None));
+    // somebody else's session, it shouldn't end up in the recent namespaces or --last
+    show_dashboard(settings, &host, false, cluster_info, events);
+}
+
+// the dashboard for whatever sends the payloads, the live connection or a recording
+fn show_dashboard(
+    settings: &Settings,
+    host: &str,
+    save_state: bool,
+    mut cluster_info: ClusterInfo,
+    events: Receiver<StreamEvent>,
+) {
+    let mut events_file = settings
+        .events_file
+        .as_deref()
+        .map(|path| diff::EventsFile::open(path).unwrap_or_else(|e| cli::exit_with_error(&e)));
+    if let Some(deployment_logs_link) = &settings.deployment_logs_link {
+        cluster_info.deployment_logs_link = deployment_logs_link.clone();
+        cluster_info.deployment_logs_link_enabled = true;
+    }
+    // the namespace table needs a terminal to read keys from, otherwise fall back to the prompts
+    let pick_in_dashboard = std::io::stdin().is_terminal();
+    let mut latest_payload = wait_for_payload(&events);
+    let mut dashboard = Dashboard::new(Vec::new(), settings);
+    dashboard.remembered = state::load().host(host);
+    let remembered = &dashboard.remembered;
+    let namespaces = match settings.last {
+        true if !remembered.last.is_empty() => remembered.last.clone(),
+        true => match remembered.recent.first() {
+            Some(namespace) => vec![namespace.clone()],
+            None => cli::exit_with_error(&format!("Error: no namespace watched on {} yet", host)),
+        },
+        false => settings.namespaces.clone(),
+    };
+    match namespaces.is_empty() {
+        false => dashboard.watch(
+            validate_namespaces(&namespaces, &cluster_info)
+                .unwrap_or_else(|e| cli::exit_with_error(&e)),
+        ),
+        true if pick_in_dashboard => dashboard.open_picker(),
+        true => {
+            clear_screen();
+            let (payload, status_table) =
+                check_cluster_status(host, latest_payload, &settings.health);
+            println!("{}", status_table);
+            latest_payload = payload;
+            dashboard.watch(vec![select_namespace(&cluster_info, &dashboard.remembered)]);
+        }
+    }
+    if !dashboard.namespaces.is_empty() {
+        let namespaces = dashboard.namespaces.clone();
+        update_state(host, save_state, &mut dashboard, |state| {
+            state.visit(host, &namespaces)
+        });
+    }
 
+    let mut tui = Tui::enter().expect("Failed to start dashboard");
+    let mut redraw = true;
+    let mut last_draw = Instant::now();
     loop {
-        let payload = match receive_payload(&mut socket, &settings.host) {
-            Ok(p) => match p {
-                Some(p) => p,
-                None => continue, // no payload on ping (tungstenite replies with pong automatically)
-            },
-            Err(e) => {
-                eprintln!("Error receiving payload: {:?}", e);
-                continue;
+        // the age of the data in the status bar changes every second, even without updates
+        if redraw || last_draw.elapsed() >= Duration::from_secs(1) {
+            tui.draw(&latest_payload, &cluster_info, &mut dashboard)
+                .expect("Failed to draw dashboard");
+            redraw = false;
+            last_draw = Instant::now();
+        }
+        match events.recv_timeout(Duration::from_millis(100)) {
+            Ok(StreamEvent::Payload(payload)) => {
+                dashboard.error = None;
+                receive_payload(&mut dashboard, &mut events_file, &latest_payload, &payload);
+                latest_payload = payload;
+                redraw = true;
             }
-        };
+            Ok(StreamEvent::Error(error)) => {
+                dashboard.error = Some(error);
+                redraw = true;
+            }
+            Ok(StreamEvent::State(state @ ConnectionState::GaveUp { .. })) => {
+                drop(tui);
+                cli::exit_with_code(&format!("Error: {}", state), EXIT_UNREACHABLE);
+            }
+            Ok(StreamEvent::State(state)) => {
+                dashboard.connection = state;
+                redraw = true;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                drop(tui);
+                eprintln!("{}", "Error: payload receiver stopped".red().bold());
+                std::process::exit(1);
+            }
+        }
+        match tui::poll_input(Duration::ZERO, dashboard.is_picking()) {
+            Some(Input::SwitchNamespace) if pick_in_dashboard => {
+                dashboard.open_picker();
+                redraw = true;
+            }
+            Some(Input::SwitchNamespace) => {
+                tui.suspend().expect("Failed to leave dashboard");
+                clear_screen();
+                dashboard.watch(vec![select_namespace(&cluster_info, &dashboard.remembered)]);
+                let namespaces = dashboard.namespaces.clone();
+                update_state(host, save_state, &mut dashboard, |state| {
+                    state.visit(host, &namespaces)
+                });
+                // render the new namespace from the latest cached payload right away
+                while let Ok(event) = events.try_recv() {
+                    match event {
+                        StreamEvent::Payload(payload) => {
+                            receive_payload(
+                                &mut dashboard,
+                                &mut events_file,
+                                &latest_payload,
+                                &payload,
+                            );
+                            latest_payload = payload;
+                        }
+                        StreamEvent::State(state) => dashboard.connection = state,
+                        StreamEvent::Error(_) => {}
+                    }
+                }
+                tui.resume().expect("Failed to resume dashboard");
+                redraw = true;
+            }
+            Some(Input::Scroll(delta)) => {
+                dashboard.scroll(delta);
+                redraw = true;
+            }
+            Some(Input::Select) if dashboard.pick() => {
+                let namespaces = dashboard.namespaces.clone();
+                update_state(host, save_state, &mut dashboard, |state| {
+                    state.visit(host, &namespaces)
+                });
+                redraw = true;
+            }
+            Some(Input::ToggleFavourite) => {
+                if let Some(namespace) = dashboard.highlighted_namespace() {
+                    update_state(host, save_state, &mut dashboard, |state| {
+                        state.toggle_favourite(host, &namespace)
+                    });
+                    redraw = true;
+                }
+            }
+            Some(Input::Select) => {} // nothing to pick, the filter matches no namespace
+            Some(Input::Back) if !dashboard.back() => break, // nothing to go back to on the start screen
+            Some(Input::Back) => redraw = true,
+            Some(Input::Type(c)) => {
+                dashboard.type_filter(c);
+                redraw = true;
+            }
+            Some(Input::Mark) => {
+                dashboard.mark();
+                redraw = true;
+            }
+            Some(Input::Erase) => {
+                dashboard.erase_filter();
+                redraw = true;
+            }
+            Some(Input::Sort(delta)) => {
+                dashboard.sort(delta);
+                redraw = true;
+            }
+            Some(Input::ReverseSort) => {
+                dashboard.reverse_sort();
+                redraw = true;
+            }
+            Some(Input::ToggleHistory) => {
+                dashboard.toggle_history();
+                redraw = true;
+            }
+            Some(Input::Resize) => redraw = true,
+            Some(Input::Quit) => break,
+            None => {}
+        }
+    }
+}
 
-        visualize_payload(payload, &ns, &cluster_info);
+// failing to save the state only costs the convenience, so it is shown instead of exiting
+fn update_state(
+    host: &str,
+    save: bool,
+    dashboard: &mut Dashboard,
+    change: impl FnOnce(&mut state::State),
+) {
+    if !save {
+        // only for this run, e.g favourites marked while watching a replay
+        let mut state = state::State::default();
+        state
+            .hosts
+            .insert(host.to_string(), dashboard.remembered.clone());
+        change(&mut state);
+        dashboard.remembered = state.host(host);
+        return;
+    }
+    match state::update(change) {
+        Ok(state) => dashboard.remembered = state.host(host),
+        Err(e) => dashboard.error = Some(e),
     }
 }
 
-fn visualize_cluster_status(
-    socket: &mut WebSocket<MaybeTlsStream<TcpStream>>,
-    host: &String,
-) -> Payload {
-    let (payload, status_table) = get_cluster_status(socket, host);
-    println!("{}", status_table);
-    payload
+// records what changed since the previous payload, like the state file a failing events file is only shown
+fn receive_payload(
+    dashboard: &mut Dashboard,
+    events_file: &mut Option<diff::EventsFile>,
+    previous: &Payload,
+    payload: &Payload,
+) {
+    let events = dashboard.receive(previous, payload, SystemTime::now());
+    if let Some(Err(e)) = events_file.as_mut().map(|file| file.write(events)) {
+        dashboard.error = Some(e);
+    }
 }
 
-fn get_cluster_status(
-    socket: &mut WebSocket<MaybeTlsStream<TcpStream>>,
-    host: &String,
-) -> (Payload, String) {
+fn run_status(settings: &Settings, once: bool, output: OutputFormat) {
+    let host = live_host(settings);
+    let mut source = live_source(host, &settings.connection);
+    source
+        .connect()
+        .unwrap_or_else(|e| cli::exit_with_error(&format!("Error connecting to host: {}", e)));
+    let cluster_info = source.cluster_info().unwrap_or_else(|e| {
+        cli::exit_with_error(&format!("Failed to fetch Simpledash Context: {}", e))
+    });
+    let print_status = |payload: Payload| match format_status(
+        display_host(host),
+        &cluster_info,
+        payload,
+        output,
+        &settings.health,
+    ) {
+        Ok(status) => println!("{}", status),
+        Err(e) => cli::exit_with_error(&format!("Error formatting status: {}", e)),
+    };
+    if once {
+        let payload = stream::first_payload(&mut source)
+            .unwrap_or_else(|e| cli::exit_with_error(&format!("Error receiving payload: {}", e)));
+        print_status(payload);
+        return;
+    }
+    let events =
+        stream::spawn_payload_receiver(source, ReconnectPolicy::new(settings.max_reconnect));
     loop {
-        let payload = match receive_payload(socket, host) {
-            Ok(p) => match p {
-                Some(p) => p,
-                None => continue, // no payload on ping (tungstenite replies with pong automatically)
-            },
-            Err(e) => {
-                eprintln!("Error receiving payload: {:?}", e);
-                continue;
+        let payload = wait_for_payload(&events);
+        if output == OutputFormat::Table {
+            clear_screen();
+        }
+        print_status(payload);
+    }
+}
+
+// blocks until the next payload, reporting errors and connection state changes on stderr meanwhile
+fn wait_for_payload(events: &Receiver<StreamEvent>) -> Payload {
+    for event in events.iter() {
+        match event {
+            StreamEvent::Payload(payload) => return payload,
+            StreamEvent::Error(error) => eprintln!("{}", error),
+            StreamEvent::State(state @ ConnectionState::GaveUp { .. }) => {
+                cli::exit_with_code(&format!("Error: {}", state), EXIT_UNREACHABLE)
             }
-        };
-        break check_cluster_status(host, payload);
+            StreamEvent::State(state) => eprintln!("{}", state),
+        }
+    }
+    cli::exit_with_code("Error: payload receiver stopped", EXIT_UNREACHABLE)
+}
+
+// stores every frame as it was received until stopped with ctrl+c, reconnecting like the dashboard
+fn run_record(settings: &Settings, out: &Path) {
+    let unreachable = |message: &str, e: Box<dyn std::error::Error>| -> ! {
+        cli::exit_with_code(&format!("{}: {}", message, e), EXIT_UNREACHABLE)
+    };
+    let host = live_host(settings);
+    let mut source = live_source(host, &settings.connection);
+    source
+        .connect()
+        .unwrap_or_else(|e| unreachable("Error connecting to host", e));
+    let context = source
+        .context()
+        .unwrap_or_else(|e| unreachable("Failed to fetch Simpledash Context", e));
+    let mut recorder = session::Recorder::create(out, display_host(host), &context)
+        .unwrap_or_else(|e| cli::exit_with_error(&e));
+
+    let (tx, events) = mpsc::channel();
+    let policy = ReconnectPolicy::new(settings.max_reconnect);
+    thread::spawn(move || {
+        stream::receive_frames(&mut source, &policy, |event| {
+            tx.send((SystemTime::now(), event)).is_ok()
+        })
+    });
+    eprintln!(
+        "recording {} to {}, press ctrl+c to stop",
+        display_host(host),
+        out.display()
+    );
+    let mut frames = 0;
+    for (received_at, event) in events.iter() {
+        match event {
+            StreamEvent::Payload(frame) => {
+                recorder
+                    .frame(received_at, &frame)
+                    .unwrap_or_else(|e| cli::exit_with_error(&e));
+                frames += 1;
+                eprint!("\rrecorded {} frames", frames);
+            }
+            StreamEvent::Error(error) => eprintln!("\n{}", error),
+            StreamEvent::State(state @ ConnectionState::GaveUp { .. }) => {
+                cli::exit_with_code(&format!("\nError: {}", state), EXIT_UNREACHABLE)
+            }
+            StreamEvent::State(state) => eprintln!("\n{}", state),
+        }
+    }
+}
+
+fn run_check(settings: &Settings) {
+    match check(
+        settings,
+        &mut live_source(live_host(settings), &settings.connection),
+    ) {
+        Ok((status_table, healthy)) => {
+            println!("{}", status_table);
+            if !healthy {
+                std::process::exit(EXIT_UNHEALTHY);
+            }
+        }
+        Err((message, code)) => cli::exit_with_code(&message, code),
+    }
+}
+
+// the status table and whether all pods are healthy, or what went wrong with the exit code for it
+fn check(
+    settings: &Settings,
+    source: &mut impl DataSource,
+) -> Result<(String, bool), (String, i32)> {
+    let unreachable = |message: &str, e: Box<dyn std::error::Error>| {
+        (format!("{}: {}", message, e), EXIT_UNREACHABLE)
+    };
+    source
+        .connect()
+        .map_err(|e| unreachable("Error connecting to host", e))?;
+    let cluster_info = source
+        .cluster_info()
+        .map_err(|e| unreachable("Failed to fetch Simpledash Context", e))?;
+    validate_namespaces(&settings.namespaces, &cluster_info).map_err(|e| (e, 1))?;
+    let mut payload =
+        stream::first_payload(source).map_err(|e| unreachable("Error receiving payload", e))?;
+    if !settings.namespaces.is_empty() {
+        payload = payload.only_namespaces(&settings.namespaces);
     }
+    let (payload, status_table) =
+        check_cluster_status(display_host(live_host(settings)), payload, &settings.health);
+    Ok((
+        status_table,
+        payload.unhealthy_pods(&settings.health).is_empty(),
+    ))
 }
 
-fn check_cluster_status(host: &String, payload: Payload) -> (Payload, String) {
-    let mut pods_in_bad_state = Vec::new();
-    for (_, value) in payload.nodes.iter() {
-        for pod in value.iter() {
-            if pod.status != "Running" && pod.status != "Succeeded" && pod.status != "Completed" {
-                pods_in_bad_state.push(pod);
+fn run_wait(settings: &Settings, deployment: &str, timeout: Duration) {
+    let unreachable = |message: &str, e: Box<dyn std::error::Error>| -> ! {
+        cli::exit_with_code(&format!("{}: {}", message, e), EXIT_UNREACHABLE)
+    };
+    let host = live_host(settings);
+    let mut source = live_source(host, &settings.connection);
+    source
+        .connect()
+        .unwrap_or_else(|e| unreachable("Error connecting to host", e));
+    let cluster_info = source
+        .cluster_info()
+        .unwrap_or_else(|e| unreachable("Failed to fetch Simpledash Context", e));
+    let namespace = validate_namespace(&settings.namespaces[0], &cluster_info)
+        .unwrap_or_else(|e| cli::exit_with_error(&e));
+
+    let deadline = Instant::now() + timeout;
+    let events =
+        stream::spawn_payload_receiver(source, ReconnectPolicy::new(settings.max_reconnect));
+    let mut summary = format!("no update received from {}", display_host(host));
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            cli::exit_with_code(
+                &format!("Timed out after {:?}, {}", timeout, summary),
+                EXIT_UNHEALTHY,
+            );
+        }
+        match events.recv_timeout(remaining) {
+            Ok(StreamEvent::Payload(payload)) => {
+                match rollout_status(&payload, &namespace, deployment, &settings.health) {
+                    Ok(ready) => {
+                        println!("{}", ready.green());
+                        return;
+                    }
+                    Err(not_ready) if not_ready != summary => {
+                        println!("{}", not_ready);
+                        summary = not_ready;
+                    }
+                    Err(_) => {}
+                }
+            }
+            Ok(StreamEvent::Error(error)) => eprintln!("{}", error),
+            Ok(StreamEvent::State(state @ ConnectionState::GaveUp { .. })) => {
+                cli::exit_with_code(&format!("Error: {}", state), EXIT_UNREACHABLE)
+            }
+            Ok(StreamEvent::State(state)) => eprintln!("{}", state),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                cli::exit_with_code("Error: payload receiver stopped", EXIT_UNREACHABLE)
             }
         }
     }
-    let total_pods = payload
+}
+
+fn rollout_status(
+    payload: &Payload,
+    namespace: &str,
+    deployment: &str,
+    health: &HealthRules,
+) -> Result<String, String> {
+    let Some(found) = payload
+        .deployments
+        .iter()
+        .find(|d| d.namespace == namespace && d.name == deployment)
+    else {
+        return Err(format!(
+            "deployment {} not found in {}",
+            deployment, namespace
+        ));
+    };
+    let pods = payload
         .nodes
         .values()
-        .map(|value| value.len())
-        .sum::<usize>();
+        .flatten()
+        .filter(|pod| pod.namespace == namespace && is_deployment_pod(&pod.name, deployment))
+        .collect::<Vec<&models::Pods>>();
+    let mut unhealthy = pods
+        .iter()
+        .filter(|pod| health.is_unhealthy(pod))
+        .map(|pod| format!("{} ({})", pod.name, pod.status))
+        .collect::<Vec<String>>();
+    unhealthy.sort();
+    if found.ready_replicas == found.replicas && unhealthy.is_empty() {
+        Ok(format!(
+            "deployment {} is ready ({}/{}), {} pods healthy",
+            deployment,
+            found.ready_replicas,
+            found.replicas,
+            pods.len()
+        ))
+    } else if unhealthy.is_empty() {
+        Err(format!(
+            "deployment {} not ready ({}/{})",
+            deployment, found.ready_replicas, found.replicas
+        ))
+    } else {
+        Err(format!(
+            "deployment {} not ready ({}/{}), unhealthy pods: {}",
+            deployment,
+            found.ready_replicas,
+            found.replicas,
+            unhealthy.join(", ")
+        ))
+    }
+}
 
-    let number_of_nodes = payload.nodes.len();
+fn is_deployment_pod(pod_name: &str, deployment: &str) -> bool {
+    // pods are named <deployment>-<replicaset hash>-<pod hash>, so api-worker-xxx-yyy is not a pod of api
+    match pod_name
+        .strip_prefix(deployment)
+        .and_then(|rest| rest.strip_prefix('-'))
+    {
+        Some(hashes) => hashes.split('-').count() == 2,
+        None => false,
+    }
+}
 
-    let mut rows = vec![vec![
+fn format_status(
+    host: &str,
+    cluster_info: &ClusterInfo,
+    payload: Payload,
+    output: OutputFormat,
+    health: &HealthRules,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let snapshot = Snapshot::new(host, cluster_info, &payload, health);
+    match output {
+        OutputFormat::Json => Ok(serde_json::to_string_pretty(&snapshot)?),
+        // one document per update, so that the output of sc status can be streamed
+        OutputFormat::Yaml => Ok(format!("---\n{}", serde_yaml::to_string(&snapshot)?)),
+        OutputFormat::Table => Ok(check_cluster_status(host, payload, health).1),
+    }
+}
+
+fn check_cluster_status(host: &str, payload: Payload, health: &HealthRules) -> (Payload, String) {
+    let rows = vec![
+        cluster_status_header(),
+        cluster_status_row(host, &payload, health),
+    ];
+    let table_display = display_cluster_status_table(rows);
+    (payload, table_display)
+}
+
+fn cluster_status_header() -> Vec<CellStruct> {
+    vec![
         "cluster".magenta().bold().cell().bold(true),
         "#unhealthy pods".magenta().bold().cell().bold(true),
         "...in namespaces".magenta().bold().cell().bold(true),
         "total pods".magenta().bold().cell().bold(true),
         "#worker nodes".magenta().bold().cell().bold(true),
         "overall status".magenta().bold().cell().bold(true),
-    ]];
+    ]
+}
+
+fn cluster_status_row(host: &str, payload: &Payload, health: &HealthRules) -> Vec<CellStruct> {
+    let pods_in_bad_state = payload.unhealthy_pods(health);
+    let total_pods = payload.total_pods();
+
+    let number_of_nodes = payload.nodes.len();
+
     if !pods_in_bad_state.is_empty() {
-        let failed_in_namespaces = pods_in_bad_state
+        let mut failed_in_namespaces = pods_in_bad_state
             .iter()
             .map(|pod| pod.namespace.clone())
             .collect::<std::collections::HashSet<String>>() // Collect into a HashSet to remove duplicates
             .into_iter()
-            .collect::<Vec<String>>()
-            .join(", ");
+            .collect::<Vec<String>>();
+        failed_in_namespaces.sort();
 
-        rows.push(vec![
+        vec![
             host.blue().bold().cell().justify(Justify::Left),
             pods_in_bad_state
                 .len()
@@ -106,13 +632,20 @@ fn check_cluster_status(host: &String, payload: Payload) -> (Payload, String) {
                 .red()
                 .cell()
                 .justify(Justify::Left),
-            failed_in_namespaces.cell().justify(Justify::Left),
+            failed_in_namespaces
+                .join(", ")
+                .cell()
+                .justify(Justify::Left),
             total_pods.to_string().blue().cell().justify(Justify::Left),
-            number_of_nodes.to_string().blue().cell().justify(Justify::Left),
+            number_of_nodes
+                .to_string()
+                .blue()
+                .cell()
+                .justify(Justify::Left),
             "BAD".bold().red().cell().justify(Justify::Left),
-        ]);
+        ]
     } else {
-        rows.push(vec![
+        vec![
             host.blue().bold().cell().justify(Justify::Left),
             pods_in_bad_state
                 .len()
@@ -122,76 +655,234 @@ fn check_cluster_status(host: &String, payload: Payload) -> (Payload, String) {
                 .justify(Justify::Left),
             "".cell().justify(Justify::Left),
             total_pods.to_string().blue().cell().justify(Justify::Left),
-            number_of_nodes.to_string().blue().cell().justify(Justify::Left),
+            number_of_nodes
+                .to_string()
+                .blue()
+                .cell()
+                .justify(Justify::Left),
             "OK".bold().green().cell().justify(Justify::Left),
-        ]);
+        ]
     }
+}
+
+// a row for a cluster we have no payload from (yet)
+fn cluster_unknown_row(host: &str, reason: &str) -> Vec<CellStruct> {
+    vec![
+        host.blue().bold().cell().justify(Justify::Left),
+        "-".cell().justify(Justify::Left),
+        reason.yellow().cell().justify(Justify::Left),
+        "-".cell().justify(Justify::Left),
+        "-".cell().justify(Justify::Left),
+        "UNKNOWN".bold().yellow().cell().justify(Justify::Left),
+    ]
+}
+
+fn display_cluster_status_table(rows: Vec<Vec<CellStruct>>) -> String {
     let table = rows.table().bold(true);
 
-    let table_display = match table.display() {
-        Ok(display) => display,
+    match table.display() {
+        Ok(display) => display.to_string(),
         Err(e) => {
             eprintln!("Error displaying cluster status table: {:?}", e);
-            return (payload, "could not visualize cluster status".to_string());
+            "could not visualize cluster status".to_string()
         }
-    };
+    }
+}
 
-    (payload, table_display.to_string())
+#[derive(Default)]
+struct ClusterState {
+    payload: Option<Payload>,
+    error: Option<String>,
 }
 
-fn receive_payload(
-    socket: &mut WebSocket<MaybeTlsStream<TcpStream>>,
-    host: &str,
-) -> Result<Option<Payload>, Box<dyn std::error::Error>> {
-    if !socket.can_read() {
-        println!("lost connection, trying to reconnect in 3 seconds...");
-        std::thread::sleep(std::time::Duration::from_secs(3));
-        *socket = client::connect_to_host(host)?;
+fn run_overview(settings: &Settings, clusters: &[Cluster]) {
+    let (tx, rx) = mpsc::channel();
+    for (i, cluster) in clusters.iter().enumerate() {
+        let mut source = live_source(&cluster.host, &cluster.connection);
+        let tx = tx.clone();
+        let policy = ReconnectPolicy::new(settings.max_reconnect);
+        thread::spawn(move || {
+            let send = |event| tx.send((i, event)).is_ok();
+            // one unreachable cluster should not take down the overview, it is retried like a lost connection
+            if let Err(e) = source.connect() {
+                send(StreamEvent::Error(format!("Error connecting: {}", e)));
+                if !stream::reconnect(&mut source, &policy, &send, e.to_string()) {
+                    return;
+                }
+            }
+            stream::receive_payloads(&mut source, &policy, send);
+        });
+    }
+    drop(tx);
+
+    let mut states = clusters
+        .iter()
+        .map(|_| ClusterState::default())
+        .collect::<Vec<ClusterState>>();
+    clear_screen();
+    println!("{}", overview_table(clusters, &states));
+    for (i, event) in rx {
+        match event {
+            StreamEvent::Payload(payload) => {
+                states[i].payload = Some(payload);
+                states[i].error = None;
+            }
+            StreamEvent::Error(error) => states[i].error = Some(error),
+            StreamEvent::State(state @ ConnectionState::GaveUp { .. }) => cli::exit_with_code(
+                &format!("Error: {} {}", display_host(&clusters[i].host), state),
+                EXIT_UNREACHABLE,
+            ),
+            StreamEvent::State(ConnectionState::Connected) => states[i].error = None,
+            StreamEvent::State(state) => states[i].error = Some(state.to_string()),
+        }
+        clear_screen();
+        println!("{}", overview_table(clusters, &states));
     }
-    let read_result = socket.read()?;
+}
 
-    if !read_result.is_empty() {
-        let payload: Payload = serde_json::from_str(&read_result.to_string())?;
-        Ok(Some(payload))
+fn overview_table(clusters: &[Cluster], states: &[ClusterState]) -> String {
+    let mut rows = vec![cluster_status_header()];
+    for (cluster, state) in clusters.iter().zip(states) {
+        let host = display_host(&cluster.host);
+        rows.push(match (&state.payload, &state.error) {
+            // an error means the payload is stale, say so instead of showing it as current
+            (_, Some(error)) => cluster_unknown_row(host, error),
+            (Some(payload), None) => cluster_status_row(host, payload, &cluster.health),
+            (None, None) => cluster_unknown_row(host, "connecting..."),
+        });
+    }
+    display_cluster_status_table(rows)
+}
+
+// globs have to match at least one namespace, plain namespaces have to exist
+fn validate_namespaces(
+    namespaces: &[String],
+    cluster_info: &ClusterInfo,
+) -> Result<Vec<String>, String> {
+    namespaces
+        .iter()
+        .map(|namespace| match glob::is_glob(namespace) {
+            true if cluster_info
+                .namespaces
+                .iter()
+                .any(|ns| glob::glob_match(namespace, ns)) =>
+            {
+                Ok(namespace.clone())
+            }
+            true => Err(format!("Error: no namespace matches '{}'", namespace)),
+            false => validate_namespace(namespace, cluster_info),
+        })
+        .collect()
+}
+
+fn validate_namespace(namespace: &str, cluster_info: &ClusterInfo) -> Result<String, String> {
+    if cluster_info.namespaces.iter().any(|ns| ns == namespace) {
+        return Ok(namespace.to_string());
+    }
+    let close_matches = close_matches(namespace, &cluster_info.namespaces);
+    if close_matches.is_empty() {
+        Err(format!(
+            "Error: namespace '{}' not found in {}",
+            namespace, cluster_info.cluster_name
+        ))
     } else {
-        Ok(None) // no payload on ping (tungstenite replies with pong automatically)
+        Err(format!(
+            "Error: namespace '{}' not found in {}, did you mean: {}?",
+            namespace,
+            cluster_info.cluster_name,
+            close_matches.join(", ")
+        ))
     }
 }
 
-fn select_namespaces_group(cluster_info: &ClusterInfo) -> (String, Vec<String>) {
+fn close_matches(name: &str, namespaces: &[String]) -> Vec<String> {
+    let name = name.to_lowercase();
+    let max_distance = (name.chars().count() / 3).max(2);
+    let mut matches = namespaces
+        .iter()
+        .map(|ns| (edit_distance(&name, &ns.to_lowercase()), ns))
+        .filter(|(distance, ns)| *distance <= max_distance || ns.to_lowercase().contains(&name))
+        .collect::<Vec<(usize, &String)>>();
+    matches.sort();
+    matches
+        .into_iter()
+        .take(5)
+        .map(|(_, ns)| ns.clone())
+        .collect()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    // levenshtein distance, one row at a time
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn select_namespaces_group(
+    cluster_info: &ClusterInfo,
+    remembered: &state::HostState,
+) -> (String, Vec<String>) {
     let namespaces = &cluster_info.namespaces;
     println!("\n{} namespaces sorted by first letter", namespaces.len());
     let grouped_namespaces = group_ns_by_first_letter(namespaces.to_vec());
     let mut sorted_keys = grouped_namespaces.keys().collect::<Vec<&char>>();
     sorted_keys.sort();
-    let selections = sorted_keys
+    // favourites and recent namespaces come first, as long as they still exist
+    let existing = |remembered: &[String]| {
+        remembered
+            .iter()
+            .filter(|ns| namespaces.contains(ns))
+            .cloned()
+            .collect::<Vec<String>>()
+    };
+    // (label in the list, description after picking it, namespaces)
+    let mut groups = vec![
+        (
+            "★ favourites".to_string(),
+            "marked as favourite".to_string(),
+            existing(&remembered.favourites),
+        ),
+        (
+            "recent".to_string(),
+            "watched recently".to_string(),
+            existing(&remembered.recent),
+        ),
+    ];
+    groups.retain(|(_, _, namespaces)| !namespaces.is_empty());
+    groups.extend(sorted_keys.iter().map(|key| {
+        (
+            key.to_string(),
+            format!("starting with '{}'", key),
+            grouped_namespaces.get(key).unwrap().to_vec(),
+        )
+    }));
+    let selections = groups
         .iter()
-        .map(|key| format!("{} ({})", key, grouped_namespaces.get(key).unwrap().len()))
+        .map(|(label, _, namespaces)| format!("{} ({})", label, namespaces.len()))
         .collect::<Vec<String>>();
     let selection_result = Select::with_theme(&ColorfulTheme::default())
         .default(0)
         .items(&selections[..])
         .interact();
 
-    let selection = match selection_result {
-        Ok(selection) => selection,
+    match selection_result {
+        Ok(selection) => {
+            let (_, description, namespaces) = groups.swap_remove(selection);
+            (description, namespaces)
+        }
         Err(e) => {
             eprintln!("Error selecting namespace group: {:?}", e);
-            return ("_".to_string(), vec!["default_namespace".to_string()]);
-        }
-    };
-
-    let key = match selections[selection].chars().next() {
-        Some(key) => key,
-        None => {
-            eprintln!("Error selecting namespace group: {:?}", "key is None");
-            return ("_".to_string(), vec!["default_namespace".to_string()]);
+            ("_".to_string(), vec!["default_namespace".to_string()])
         }
-    };
-    (
-        key.to_string(),
-        grouped_namespaces.get(&key).unwrap().to_vec(),
-    )
+    }
 }
 
 fn group_ns_by_first_letter(ns: Vec<String>) -> HashMap<char, Vec<String>> {
@@ -206,14 +897,10 @@ fn group_ns_by_first_letter(ns: Vec<String>) -> HashMap<char, Vec<String>> {
     ns_groups
 }
 
-fn select_namespace(cluster_info: &ClusterInfo) -> String {
-    let (letter, namespaces) = select_namespaces_group(cluster_info);
+fn select_namespace(cluster_info: &ClusterInfo, remembered: &state::HostState) -> String {
+    let (group, namespaces) = select_namespaces_group(cluster_info, remembered);
     let selections = &namespaces[..];
-    println!(
-        "{} namespaces starting with '{}':",
-        selections.len(),
-        letter
-    );
+    println!("{} namespaces {}:", selections.len(), group);
     let selection_result = Select::with_theme(&ColorfulTheme::default())
         .default(0)
         .items(selections)
@@ -229,281 +916,154 @@ fn select_namespace(cluster_info: &ClusterInfo) -> String {
     namespaces[selection].to_string()
 }
 
-fn visualize_payload(payload: Payload, namespace: &str, cluster_info: &ClusterInfo) {
-    clear_screen();
-    print_endpoints(&payload, namespace);
-    print_deployments(&payload, namespace, cluster_info);
-    print_pods_table(&payload, namespace, cluster_info);
-}
-
-fn print_endpoints(payload: &Payload, namespace: &str) {
-    println!("{}", "Endpoints:".magenta().bold());
-    println!("{}", get_endpoints_visualization(payload, namespace));
-}
-fn get_endpoints_visualization(payload: &Payload, namespace: &str) -> String {
-    let mut result = String::new();
-    if let Some(ingresses) = &payload.ingresses {
-        for ingress in ingresses {
-            if ingress.namespace != *namespace {
-                continue;
-            }
-            result.push_str(&format!(
-                "{}{} ({})\n",
-                "https://".bold().blue(), // .. :/.. Its been our case so far that everything is TLS, simpledash server has to return the protocol in use
-                ingress.endpoint.bold().blue(),
-                ingress.ip
-            ));
-        }
-    }
-    result
-}
-fn print_deployments(payload: &Payload, namespace: &str, cluster_info: &ClusterInfo) {
-    println!("{}", "Deployments:".magenta().bold());
-    println!(
-        "{}",
-        get_deployments_visualization(payload, namespace, cluster_info)
-    );
-}
-fn get_deployments_visualization(
-    payload: &Payload,
-    namespace: &str,
-    cluster_info: &ClusterInfo,
-) -> String {
-    let mut result = String::new();
-    for deployment in payload.deployments.iter() {
-        if deployment.namespace != *namespace {
-            continue;
-        }
-        if cluster_info.deployment_logs_link_enabled {
-            let link_url = cluster_info
-                .deployment_logs_link
-                .replace("DEPLOYMENT_NAME_PLACEHOLDER", &deployment.name)
-                .replace("DEPLOYMENT_NAMESPACE_PLACEHOLDER", &deployment.namespace);
-            result.push_str(&format!(
-                "{} ({}/{}) {}\n",
-                deployment.name,
-                deployment.ready_replicas,
-                deployment.replicas,
-                cli::make_link(link_url, "view logs".to_string())
-                    .bold()
-                    .blue()
-            ));
-        } else {
-            result.push_str(&format!(
-                "{} ({}/{})\n",
-                deployment.name, deployment.ready_replicas, deployment.replicas
-            ));
-        }
-    }
-    result
-}
-fn print_pods_table(payload: &Payload, namespace: &str, cluster_info: &ClusterInfo) {
-    let (pod_viz, no_pods) = get_pods_visualization(payload, namespace);
-    println!("{}({})", "Pods:".magenta().bold(), no_pods.to_string().as_str().blue());
-    println!("{}", pod_viz);
-    println!(
-        "{} in {} as per {}",
-        namespace.bold().magenta().on_black(),
-        cluster_info.cluster_name.bold().magenta().on_black(),
-        payload.timestamp.bold().yellow()
-    );
-}
-fn get_pods_visualization(payload: &Payload, namespace: &str) -> (String, usize) {
-    let mut pod_rows = vec![vec![
-        "node".cell().bold(true),
-        "pod name".cell().bold(true),
-        "status".cell().bold(true),
-        "tag".cell().bold(true),
-    ]];
-
-    let mut pairs: Vec<_> = payload.nodes.clone().into_iter().collect();
-    pairs.sort_by_key(|pair| pair.0.clone());
-    let mut total_no_pods = 0;
-    for (key, value) in pairs {
-        let mut pods_in_namespace: Vec<&Pods> = value
-            .iter()
-            .filter(|pod| pod.namespace == *namespace)
-            .collect();
-        total_no_pods += pods_in_namespace.len();
-        pods_in_namespace.sort();
-        for pod in pods_in_namespace {
-            let colored_status: ColoredString;
-            if pod.status == "Running" || pod.status == "Succeeded" || pod.status == "Completed" {
-                colored_status = pod.status.green();
-            } else if pod.status == "Pending"
-                || pod.status == "ContainerCreating"
-                || pod.status == "PodInitializing"
-            {
-                colored_status = pod.status.yellow();
-            } else {
-                colored_status = pod.status.red();
-            }
-
-            let pod_image_tag = pod.image.split(':').last().unwrap_or("unknown");
-
-            pod_rows.push(vec![
-                key.clone().cell(),
-                pod.name.clone().cell(),
-                colored_status.cell().justify(Justify::Right),
-                pod_image_tag.cell(),
-            ]);
-        }
-    }
-    let pod_table = pod_rows.table().bold(true);
-    let pod_table_display = match pod_table.display() {
-        Ok(display) => display,
-        Err(e) => {
-            eprintln!("Error displaying pod table: {:?}", e);
-            return ("could not visualize pods".to_string(), 0);
-        }
-    };
-    (pod_table_display.to_string(), total_no_pods)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use models::{ClusterInfo, Deployment};
+    use source::{FakeSource, Step};
 
     #[test]
-    fn get_deployments_visualization_visualizing_only_selected_namespace() {
+    fn check_runs_against_a_data_source() {
         // Arrange
-        let payload = Payload {
-            deployments: vec![
-                Deployment {
-                    name: "deployment1".to_string(),
-                    namespace: "namespace1".to_string(),
-                    ready_replicas: 2,
-                    replicas: 3,
-                    ..Default::default()
-                },
-                Deployment {
-                    name: "deployment2".to_string(),
-                    namespace: "namespace2".to_string(),
-                    ready_replicas: 1,
-                    replicas: 1,
-                    ..Default::default()
-                },
-            ],
-            ..Default::default()
-        };
-        let cluster_info = ClusterInfo {
-            deployment_logs_link_enabled: false,
+        let context = r#"{"ClusterName":"prod","Namespaces":["payments","monitoring"],"DeploymentLogsLinkEnabled":false,"DeploymentLogsLink":""}"#;
+        let payload = r#"{"Nodes":{"node1":[
+            {"Namespace":"payments","Name":"api-1","Image":"api:1.0","Status":"Running"},
+            {"Namespace":"monitoring","Name":"grafana-1","Image":"grafana:10","Status":"CrashLoopBackOff"}
+        ]},"Ingresses":null,"Timestamp":"2024-05-14T10:12:03Z","Deployments":[]}"#;
+        let settings = |namespaces: &[&str]| Settings {
+            host: Some(Url::parse("https://host1").unwrap()),
+            namespaces: namespaces.iter().map(|ns| ns.to_string()).collect(),
             ..Default::default()
         };
+        let source = |steps| FakeSource::new(context, steps);
 
         // Act
-        let visualization = get_deployments_visualization(&payload, "namespace1", &cluster_info);
+        let cluster = check(
+            &settings(&[]),
+            &mut source(vec![Step::Frame(payload.to_string())]),
+        );
+        let payments = check(
+            &settings(&["payments"]),
+            &mut source(vec![Step::Frame(payload.to_string())]),
+        );
+        let unknown = check(
+            &settings(&["paymnts"]),
+            &mut source(vec![Step::Frame(payload.to_string())]),
+        );
+        let unreachable = check(&settings(&[]), &mut source(vec![Step::Refuse]));
 
         // Assert
-        // contains this
-        assert!(visualization.contains("deployment1 (2/3)"));
-        // and not this...
-        assert!(!visualization.contains("deployment2 (1/1)"));
+        let (table, healthy) = cluster.unwrap();
+        assert!(!healthy);
+        assert!(table.contains("BAD"));
+        assert!(payments.unwrap().1);
+        assert_eq!(unknown.unwrap_err().1, 1);
+        assert_eq!(unreachable.unwrap_err().1, EXIT_UNREACHABLE);
     }
 
     #[test]
-    fn get_endpoints_visualization_visualizing_only_selected_namespace() {
+    fn check_cluster_status_sets_overall_status_to_bad_if_one_pod_is_crashloopbackoff() {
         // Arrange
         let payload = Payload {
-            ingresses: Some(vec![
-                models::Ingress {
-                    endpoint: "endpoint1".to_string(),
+            nodes: std::collections::HashMap::from_iter(vec![(
+                "node1".to_string(),
+                vec![models::Pods {
                     namespace: "namespace1".to_string(),
-                    ip: "172.23.1.205".to_string(),
-                },
-                models::Ingress {
-                    endpoint: "endpoint2".to_string(),
-                    namespace: "namespace2".to_string(),
-                    ip: "172.23.1.205".to_string(),
-                },
-            ]),
+                    name: "pod1".to_string(),
+                    status: models::PodStatus::CrashLoopBackOff,
+                    image: "image1:tag1".to_string(),
+                }],
+            )]),
             ..Default::default()
         };
+        let host = "host1".to_string();
 
         // Act
-        let visualization = get_endpoints_visualization(&payload, "namespace1");
+        let (_, status_table) = check_cluster_status(&host, payload, &HealthRules::default());
 
         // Assert
-        // contains this
-        println!("{}", visualization);
-
-        let expected = format!(
-            "{}{} ({})\n",
-            "https://".bold().blue(),
-            "endpoint1".bold().blue(),
-            "172.23.1.205"
-        );
-        assert!(visualization.eq(&expected));
-        // and not this
-        let not_expected = format!(
-            "{}{} ({})\n",
-            "https://".bold().blue(),
-            "endpoint2".bold().blue(),
-            "172.23.1.205"
-        );
-        assert!(!visualization.contains(&not_expected));
+        assert!(status_table.contains("BAD"));
+        assert!(status_table.contains("host1"));
+        assert!(status_table.contains("1"));
+        assert!(status_table.contains("namespace1"));
     }
-
     #[test]
-    fn get_pods_visualization_visualizing_only_selected_namespace() {
+    fn check_cluster_status_sets_overall_status_to_ok_if_all_pods_ok() {
         // Arrange
         let payload = Payload {
-            nodes: std::collections::HashMap::from_iter(vec![
-                (
-                    "node1".to_string(),
-                    vec![
-                        models::Pods {
-                            namespace: "namespace1".to_string(),
-                            name: "pod1".to_string(),
-                            status: "Running".to_string(),
-                            image: "image1:tag1".to_string(),
-                        },
-                        models::Pods {
-                            namespace: "namespace2".to_string(),
-                            name: "pod2".to_string(),
-                            status: "Running".to_string(),
-                            image: "image2:tag2".to_string(),
-                        },
-                    ],
-                ),
-                (
-                    "node2".to_string(),
-                    vec![models::Pods {
+            nodes: std::collections::HashMap::from_iter(vec![(
+                "node1".to_string(),
+                vec![
+                    models::Pods {
+                        namespace: "namespace1".to_string(),
+                        name: "pod1".to_string(),
+                        status: models::PodStatus::Running,
+                        image: "image1:tag1".to_string(),
+                    },
+                    models::Pods {
+                        namespace: "namespace1".to_string(),
+                        name: "pod2".to_string(),
+                        status: models::PodStatus::Succeeded,
+                        image: "image2:tag2".to_string(),
+                    },
+                    models::Pods {
                         namespace: "namespace1".to_string(),
                         name: "pod3".to_string(),
-                        status: "Running".to_string(),
+                        status: models::PodStatus::Completed,
                         image: "image3:tag3".to_string(),
-                    }],
-                ),
-            ]),
+                    },
+                ],
+            )]),
             ..Default::default()
         };
+        let host = "host1".to_string();
 
         // Act
-        let (visualization, no_of_pods) = get_pods_visualization(&payload, "namespace1");
+        let (_, status_table) = check_cluster_status(&host, payload, &HealthRules::default());
 
         // Assert
-        
-        // no_of_pods is 2
-        assert!(no_of_pods == 2);
-
-        // contains this (pod 1 and pod 3 in node 1 and node 2)
-        assert!(visualization.contains("node1"));
-        assert!(visualization.contains("pod1"));
-        assert!(visualization.contains("Running"));
-        assert!(visualization.contains("tag1"));
-        assert!(visualization.contains("node2"));
-        assert!(visualization.contains("pod3"));
-        assert!(visualization.contains("Running"));
-        assert!(visualization.contains("tag3"));
-        // and not this (pod2)
-        assert!(!visualization.contains("pod2"));
-        assert!(!visualization.contains("tag2"));
+        assert!(status_table.contains("OK"));
+        assert!(status_table.contains("host1"));
+        assert!(status_table.contains("0"));
     }
 
     #[test]
-    fn check_cluster_status_sets_overall_status_to_bad_if_one_pod_is_crashloopbackoff() {
+    fn validate_namespace_accepts_existing_namespace() {
+        // Arrange
+        let cluster_info = ClusterInfo {
+            namespaces: vec!["namespace1".to_string(), "namespace2".to_string()],
+            ..Default::default()
+        };
+
+        // Act
+        let result = validate_namespace("namespace2", &cluster_info);
+
+        // Assert
+        assert_eq!(result, Ok("namespace2".to_string()));
+    }
+    #[test]
+    fn validate_namespace_lists_close_matches_if_namespace_is_missing() {
+        // Arrange
+        let cluster_info = ClusterInfo {
+            cluster_name: "cluster1".to_string(),
+            namespaces: vec![
+                "payments".to_string(),
+                "payments-worker".to_string(),
+                "monitoring".to_string(),
+            ],
+            ..Default::default()
+        };
+
+        // Act
+        let result = validate_namespace("paymnets", &cluster_info);
+
+        // Assert
+        let error = result.unwrap_err();
+        assert!(error.contains("'paymnets' not found in cluster1"));
+        assert!(error.contains("payments"));
+        assert!(!error.contains("monitoring"));
+    }
+    #[test]
+    fn format_status_as_json_uses_the_documented_schema() {
         // Arrange
         let payload = Payload {
             nodes: std::collections::HashMap::from_iter(vec![(
@@ -511,25 +1071,43 @@ mod tests {
                 vec![models::Pods {
                     namespace: "namespace1".to_string(),
                     name: "pod1".to_string(),
-                    status: "CrashLoopBackOff".to_string(),
+                    status: models::PodStatus::CrashLoopBackOff,
                     image: "image1:tag1".to_string(),
                 }],
             )]),
+            timestamp: "2023-10-18 12:00:00".to_string(),
+            ..Default::default()
+        };
+        let cluster_info = ClusterInfo {
+            cluster_name: "cluster1".to_string(),
             ..Default::default()
         };
-        let host = "host1".to_string();
 
         // Act
-        let (_, status_table) = check_cluster_status(&host, payload);
+        let json = format_status(
+            "host1",
+            &cluster_info,
+            payload,
+            OutputFormat::Json,
+            &HealthRules::default(),
+        )
+        .unwrap();
 
         // Assert
-        assert!(status_table.contains("BAD"));
-        assert!(status_table.contains("host1"));
-        assert!(status_table.contains("1"));
-        assert!(status_table.contains("namespace1"));
+        let snapshot: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(snapshot["schema_version"], 1);
+        assert_eq!(snapshot["host"], "host1");
+        assert_eq!(snapshot["cluster_name"], "cluster1");
+        assert_eq!(snapshot["timestamp"], "2023-10-18 12:00:00");
+        assert_eq!(snapshot["status"], "BAD");
+        assert_eq!(snapshot["total_pods"], 1);
+        assert_eq!(snapshot["unhealthy_pods"][0]["name"], "pod1");
+        assert_eq!(snapshot["nodes"]["node1"][0]["status"], "CrashLoopBackOff");
+        assert_eq!(snapshot["deployments"], serde_json::json!([]));
+        assert_eq!(snapshot["ingresses"], serde_json::json!([]));
     }
     #[test]
-    fn check_cluster_status_sets_overall_status_to_ok_if_all_pods_ok() {
+    fn rollout_status_is_ready_when_replicas_are_ready_and_pods_healthy() {
         // Arrange
         let payload = Payload {
             nodes: std::collections::HashMap::from_iter(vec![(
@@ -537,34 +1115,102 @@ mod tests {
                 vec![
                     models::Pods {
                         namespace: "namespace1".to_string(),
-                        name: "pod1".to_string(),
-                        status: "Running".to_string(),
-                        image: "image1:tag1".to_string(),
-                    },
-                    models::Pods {
-                        namespace: "namespace1".to_string(),
-                        name: "pod2".to_string(),
-                        status: "Succeeded".to_string(),
-                        image: "image2:tag2".to_string(),
+                        name: "api-7d9f8b6c5d-x2x4z".to_string(),
+                        status: models::PodStatus::Running,
+                        image: "api:v2".to_string(),
                     },
                     models::Pods {
                         namespace: "namespace1".to_string(),
-                        name: "pod3".to_string(),
-                        status: "Completed".to_string(),
-                        image: "image3:tag3".to_string(),
+                        name: "api-worker-5c6b7d8f9-abcde".to_string(),
+                        status: models::PodStatus::CrashLoopBackOff,
+                        image: "api-worker:v2".to_string(),
                     },
                 ],
             )]),
+            deployments: vec![models::Deployment {
+                name: "api".to_string(),
+                namespace: "namespace1".to_string(),
+                ready_replicas: 1,
+                replicas: 1,
+            }],
             ..Default::default()
         };
-        let host = "host1".to_string();
 
         // Act
-        let (_, status_table) = check_cluster_status(&host, payload);
+        let status = rollout_status(&payload, "namespace1", "api", &HealthRules::default());
 
         // Assert
-        assert!(status_table.contains("OK"));
-        assert!(status_table.contains("host1"));
-        assert!(status_table.contains("0"));
+        assert_eq!(
+            status,
+            Ok("deployment api is ready (1/1), 1 pods healthy".to_string())
+        );
+    }
+    #[test]
+    fn rollout_status_lists_unhealthy_pods_when_not_ready() {
+        // Arrange
+        let payload = Payload {
+            nodes: std::collections::HashMap::from_iter(vec![(
+                "node1".to_string(),
+                vec![models::Pods {
+                    namespace: "namespace1".to_string(),
+                    name: "api-7d9f8b6c5d-x2x4z".to_string(),
+                    status: models::PodStatus::CrashLoopBackOff,
+                    image: "api:v2".to_string(),
+                }],
+            )]),
+            deployments: vec![models::Deployment {
+                name: "api".to_string(),
+                namespace: "namespace1".to_string(),
+                ready_replicas: 1,
+                replicas: 2,
+            }],
+            ..Default::default()
+        };
+
+        // Act
+        let status = rollout_status(&payload, "namespace1", "api", &HealthRules::default());
+
+        // Assert
+        assert_eq!(
+            status,
+            Err(
+                "deployment api not ready (1/2), unhealthy pods: api-7d9f8b6c5d-x2x4z (CrashLoopBackOff)"
+                    .to_string()
+            )
+        );
+    }
+    #[test]
+    fn overview_table_has_one_row_per_cluster() {
+        // Arrange
+        let clusters = ["https://host1", "https://host2", "https://host3"]
+            .map(|host| Cluster {
+                host: Url::parse(host).unwrap(),
+                health: HealthRules::default(),
+                connection: Connection::default(),
+            })
+            .to_vec();
+        let states = vec![
+            ClusterState {
+                payload: Some(Payload::default()),
+                error: None,
+            },
+            ClusterState {
+                payload: None,
+                error: Some("Error connecting: connection refused".to_string()),
+            },
+            ClusterState::default(),
+        ];
+
+        // Act
+        let table = overview_table(&clusters, &states);
+
+        // Assert
+        assert!(table.contains("host1"));
+        assert!(table.contains("OK"));
+        assert!(table.contains("host2"));
+        assert!(table.contains("connection refused"));
+        assert!(table.contains("host3"));
+        assert!(table.contains("connecting..."));
+        assert_eq!(table.matches("UNKNOWN").count(), 2);
     }
 }
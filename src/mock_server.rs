@@ -0,0 +1,445 @@
+// a stand-in for the simpledash server for development and the tests: serves /context and /ws
+// on one port and plays a scenario of payloads, pauses and dropped connections
+use crate::duration::parse_duration;
+use crate::session::{self, Session};
+use native_tls::{Identity, TlsAcceptor};
+use serde::Deserialize;
+use serde_json::json;
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use tungstenite::{Message, WebSocket};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Send(String),
+    Wait(Duration),
+    // closes the connection without a close frame, like a lost connection.
+    // the client has to reconnect to get the rest of the scenario
+    Drop,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub context: String,
+    pub steps: Vec<Step>,
+    // starts over after the last step, otherwise the connection just stays open
+    pub repeat: bool,
+    // the Authorization header the server insists on, like an auth proxy in front of it
+    pub auth: Option<String>,
+}
+
+// scenario.toml, paths are relative to it
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScenarioFile {
+    context: String,
+    #[serde(default)]
+    repeat: bool,
+    auth: Option<String>,
+    #[serde(default, rename = "step")]
+    steps: Vec<StepFile>,
+}
+
+// waits first, then sends the payload or drops the connection
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StepFile {
+    wait: Option<String>,
+    payload: Option<String>,
+    #[serde(default)]
+    drop: bool,
+}
+
+impl Scenario {
+    // a scenario.toml or a recording made with sc record
+    pub fn load(path: &Path) -> Result<Scenario, String> {
+        let read = |path: &Path| {
+            std::fs::read_to_string(path)
+                .map_err(|e| format!("Error reading {}: {}", path.display(), e))
+        };
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "jsonl")
+        {
+            return Ok(Scenario::from_recording(session::load(path)?));
+        }
+        let file: ScenarioFile = toml::from_str(&read(path)?)
+            .map_err(|e| format!("Error: invalid scenario {}: {}", path.display(), e))?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let mut steps = Vec::new();
+        for step in file.steps {
+            if let Some(wait) = step.wait {
+                steps.push(Step::Wait(parse_duration(&wait).ok_or_else(|| {
+                    format!("Error: invalid wait '{}' (e.g 500ms or 3s)", wait)
+                })?));
+            }
+            if let Some(payload) = step.payload {
+                steps.push(Step::Send(read(&dir.join(payload))?));
+            }
+            if step.drop {
+                steps.push(Step::Drop);
+            }
+        }
+        Ok(Scenario {
+            context: read(&dir.join(file.context))?,
+            steps,
+            repeat: file.repeat,
+            auth: file.auth,
+        })
+    }
+    // the frames with the pauses between them as they were recorded
+    fn from_recording(session: Session) -> Scenario {
+        let mut steps = Vec::new();
+        let mut previous: Option<SystemTime> = None;
+        for (received_at, frame) in session.frames {
+            if let Some(previous) = previous {
+                let pause = received_at.duration_since(previous).unwrap_or_default();
+                steps.push(Step::Wait(pause));
+            }
+            previous = Some(received_at);
+            steps.push(Step::Send(frame));
+        }
+        Scenario {
+            context: session.context,
+            steps,
+            repeat: false,
+            auth: None,
+        }
+    }
+    // a pod crashing, a deployment scaling up and a dropped connection, over and over
+    pub fn demo() -> Scenario {
+        let pod = |name: &str, status: &str| json!({"Namespace": "payments", "Name": name, "Image": "api:1.4.2", "Status": status});
+        let payload = |pods: Vec<serde_json::Value>, ready: u32, replicas: u32| {
+            json!({
+                "Nodes": {
+                    "node1": pods,
+                    "node2": [
+                        {"Namespace": "monitoring", "Name": "grafana-0", "Image": "grafana:10.4", "Status": "Running"}
+                    ]
+                },
+                "Ingresses": [
+                    {"Namespace": "payments", "Endpoint": "payments.example.com", "Ip": "10.0.0.1"},
+                    {"Namespace": "monitoring", "Endpoint": "grafana.example.com", "Ip": "10.0.0.2"}
+                ],
+                "Timestamp": "",
+                "Deployments": [
+                    {"Namespace": "payments", "Name": "api", "Replicas": replicas, "ReadyReplicas": ready},
+                    {"Namespace": "monitoring", "Name": "grafana", "Replicas": 1, "ReadyReplicas": 1}
+                ]
+            })
+            .to_string()
+        };
+        let pause = Step::Wait(Duration::from_secs(3));
+        Scenario {
+            context: json!({
+                "ClusterName": "mock",
+                "Namespaces": ["default", "monitoring", "payments"],
+                "DeploymentLogsLinkEnabled": false,
+                "DeploymentLogsLink": ""
+            })
+            .to_string(),
+            steps: vec![
+                Step::Send(payload(
+                    vec![pod("api-1", "Running"), pod("api-2", "Running")],
+                    2,
+                    2,
+                )),
+                pause.clone(),
+                Step::Send(payload(
+                    vec![pod("api-1", "Running"), pod("api-2", "CrashLoopBackOff")],
+                    1,
+                    2,
+                )),
+                pause.clone(),
+                Step::Send(payload(
+                    vec![
+                        pod("api-1", "Running"),
+                        pod("api-2", "CrashLoopBackOff"),
+                        pod("api-3", "ContainerCreating"),
+                    ],
+                    1,
+                    3,
+                )),
+                pause.clone(),
+                Step::Send(payload(
+                    vec![pod("api-1", "Running"), pod("api-3", "Running")],
+                    2,
+                    3,
+                )),
+                pause.clone(),
+                Step::Drop,
+                Step::Send(payload(
+                    vec![
+                        pod("api-1", "Running"),
+                        pod("api-3", "Running"),
+                        pod("api-4", "Running"),
+                    ],
+                    3,
+                    3,
+                )),
+                pause,
+            ],
+            repeat: true,
+            auth: None,
+        }
+    }
+}
+
+pub struct MockServer {
+    pub addr: SocketAddr,
+    tls: bool,
+}
+
+impl MockServer {
+    // 127.0.0.1:0 picks a free port
+    pub fn start(addr: &str, scenario: Scenario) -> io::Result<MockServer> {
+        MockServer::serve(addr, scenario, None)
+    }
+    // https and wss with this certificate (chain) and its pkcs8 key, both pem
+    pub fn start_tls(
+        addr: &str,
+        scenario: Scenario,
+        cert: &[u8],
+        key: &[u8],
+    ) -> Result<MockServer, Box<dyn Error>> {
+        let acceptor = TlsAcceptor::new(Identity::from_pkcs8(cert, key)?)?;
+        Ok(MockServer::serve(addr, scenario, Some(acceptor))?)
+    }
+    fn serve(addr: &str, scenario: Scenario, tls: Option<TlsAcceptor>) -> io::Result<MockServer> {
+        let listener = TcpListener::bind(addr)?;
+        let server = MockServer {
+            addr: listener.local_addr()?,
+            tls: tls.is_some(),
+        };
+        let scenario = Arc::new(scenario);
+        // shared by all connections, so that a reconnect continues where the dropped connection left off
+        let position = Arc::new(Mutex::new(0));
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (scenario, position, tls) = (scenario.clone(), position.clone(), tls.clone());
+                thread::spawn(move || {
+                    let _ = handle(stream, tls.as_ref(), &scenario, &position);
+                });
+            }
+        });
+        Ok(server)
+    }
+    pub fn url(&self) -> String {
+        match self.tls {
+            true => format!("https://{}", self.addr),
+            false => format!("http://{}", self.addr),
+        }
+    }
+}
+
+fn handle(
+    stream: TcpStream,
+    tls: Option<&TlsAcceptor>,
+    scenario: &Scenario,
+    position: &Mutex<usize>,
+) -> Result<(), Box<dyn Error>> {
+    // to set the read timeout once the stream is wrapped in tls
+    let tcp = stream.try_clone()?;
+    match tls {
+        Some(acceptor) => serve_request(acceptor.accept(stream)?, &tcp, scenario, position),
+        None => serve_request(stream, &tcp, scenario, position),
+    }
+}
+
+fn serve_request<S: Read + Write + 'static>(
+    mut stream: S,
+    tcp: &TcpStream,
+    scenario: &Scenario,
+    position: &Mutex<usize>,
+) -> Result<(), Box<dyn Error>> {
+    let mut head = Vec::new();
+    let mut buffer = [0; 1024];
+    while !head.windows(4).any(|end| end == b"\r\n\r\n") {
+        match stream.read(&mut buffer)? {
+            0 => break,
+            read => head.extend_from_slice(&buffer[..read]),
+        }
+    }
+    let request = String::from_utf8_lossy(&head).to_string();
+    let authorization = request.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.eq_ignore_ascii_case("authorization")
+            .then(|| value.trim())
+    });
+    if scenario.auth.is_some() && scenario.auth.as_deref() != authorization {
+        return respond(&mut stream, "401 Unauthorized", "unauthorized");
+    }
+    // under any base path and with any query, like a simpledash behind a path based ingress
+    let target = request.split_whitespace().nth(1).unwrap_or_default();
+    let path = target.split('?').next().unwrap_or_default();
+    match path.rsplit('/').next() {
+        Some("context") => respond(&mut stream, "200 OK", &scenario.context),
+        Some("ws") => {
+            tcp.set_read_timeout(Some(Duration::from_millis(100)))?;
+            // the handshake reads the request itself, so it gets the head again
+            let stream = Prefixed {
+                head: io::Cursor::new(head),
+                stream,
+            };
+            play(tungstenite::accept(stream)?, scenario, position)
+        }
+        _ => respond(&mut stream, "404 Not Found", "not found"),
+    }
+}
+
+fn respond(stream: &mut impl Write, status: &str, body: &str) -> Result<(), Box<dyn Error>> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+// what was read already, followed by the rest of the stream
+struct Prefixed<S> {
+    head: io::Cursor<Vec<u8>>,
+    stream: S,
+}
+
+impl<S: Read> Read for Prefixed<S> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        match self.head.read(buffer)? {
+            0 => self.stream.read(buffer),
+            read => Ok(read),
+        }
+    }
+}
+
+impl<S: Write> Write for Prefixed<S> {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.stream.write(buffer)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+fn play<S: Read + Write>(
+    mut socket: WebSocket<S>,
+    scenario: &Scenario,
+    position: &Mutex<usize>,
+) -> Result<(), Box<dyn Error>> {
+    loop {
+        let step = {
+            let mut position = position.lock().map_err(|_| "scenario lock poisoned")?;
+            if *position >= scenario.steps.len() && scenario.repeat {
+                *position = 0;
+            }
+            let step = scenario.steps.get(*position).cloned();
+            *position += step.is_some() as usize;
+            step
+        };
+        match step {
+            Some(Step::Send(payload)) => socket.send(Message::Text(with_timestamp(&payload)))?,
+            Some(Step::Wait(pause)) => idle(&mut socket, pause)?,
+            Some(Step::Drop) => return Ok(()),
+            None => idle(&mut socket, Duration::from_secs(1))?,
+        }
+    }
+}
+
+// keeps reading while waiting, so that pings get answered
+fn idle<S: Read + Write>(socket: &mut WebSocket<S>, pause: Duration) -> Result<(), Box<dyn Error>> {
+    let deadline = Instant::now() + pause;
+    while Instant::now() < deadline {
+        match socket.read() {
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+// payloads are sent as if they were new, otherwise sc shows fixtures and recordings as stale
+fn with_timestamp(payload: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(payload) {
+        Ok(mut value) if value.get("Timestamp").is_some() => {
+            value["Timestamp"] = json!(chrono::Local::now().to_rfc3339());
+            value.to_string()
+        }
+        _ => payload.to_string(), // sent as is, e.g to test how sc handles broken frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ClusterInfo, Payload};
+
+    fn node(name: &str) -> String {
+        format!(
+            r#"{{"Nodes":{{"{}":[]}},"Ingresses":null,"Timestamp":"","Deployments":[]}}"#,
+            name
+        )
+    }
+
+    #[test]
+    fn mock_server_scenarios_load_from_files_and_the_demo_sends_valid_payloads() {
+        // Arrange
+        let dir = std::env::temp_dir().join(format!("sc-scenario-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("context.json"), "{}").unwrap();
+        std::fs::write(dir.join("healthy.json"), node("node1")).unwrap();
+        std::fs::write(
+            dir.join("scenario.toml"),
+            "context = \"context.json\"\n\n[[step]]\npayload = \"healthy.json\"\n\n[[step]]\nwait = \"500ms\"\ndrop = true\n",
+        )
+        .unwrap();
+        let mut recorder =
+            session::Recorder::create(&dir.join("session.jsonl"), "https://host1", "{}").unwrap();
+        let start = SystemTime::now();
+        recorder.frame(start, &node("node1")).unwrap();
+        (recorder.frame(start + Duration::from_secs(2), &node("node2"))).unwrap();
+
+        // Act
+        let scenario = Scenario::load(&dir.join("scenario.toml"));
+        let recording = Scenario::load(&dir.join("session.jsonl"));
+        let demo = Scenario::demo();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Assert
+        let scenario = scenario.unwrap();
+        assert_eq!(scenario.context, "{}");
+        assert_eq!(
+            scenario.steps,
+            vec![
+                Step::Send(node("node1")),
+                Step::Wait(Duration::from_millis(500)),
+                Step::Drop,
+            ]
+        );
+        assert_eq!(
+            recording.unwrap().steps,
+            vec![
+                Step::Send(node("node1")),
+                Step::Wait(Duration::from_secs(2)),
+                Step::Send(node("node2")),
+            ]
+        );
+        assert!(serde_json::from_str::<ClusterInfo>(&demo.context).is_ok());
+        for step in demo.steps {
+            if let Step::Send(payload) = step {
+                assert!(serde_json::from_str::<Payload>(&payload).is_ok());
+            }
+        }
+    }
+}
@@ -1,8 +1,79 @@
-use serde::Deserialize;
-
+use crate::client::Connection;
+use crate::glob::glob_match;
+use crate::health::HealthRules;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use url::Url;
 
+#[derive(Default)]
 pub struct Settings {
-    pub host: String,
+    // parsed once, the client derives /context and /ws from it. None for sc replay
+    pub host: Option<Url>,
+    pub namespaces: Vec<String>,
+    pub last: bool,
+    pub command: Command,
+    pub theme: Theme,
+    pub deployment_logs_link: Option<String>,
+    pub endpoint_link: Option<String>,
+    pub max_reconnect: Option<u32>,
+    pub stale_after: Duration,
+    // how long changes between payloads stay highlighted in the dashboard
+    pub highlight_for: Duration,
+    pub events_file: Option<PathBuf>,
+    pub health: HealthRules,
+    pub connection: Connection,
+}
+
+// a cluster in sc overview, each profile can have its own health rules
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cluster {
+    pub host: Url,
+    pub health: HealthRules,
+    pub connection: Connection,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    Mono,
+}
+
+#[derive(Default)]
+pub enum Command {
+    #[default]
+    Dashboard,
+    Status {
+        once: bool,
+        output: OutputFormat,
+    },
+    Check,
+    Overview {
+        clusters: Vec<Cluster>,
+    },
+    Wait {
+        deployment: String,
+        timeout: std::time::Duration,
+    },
+    Record {
+        out: PathBuf,
+    },
+    Replay {
+        session: PathBuf,
+        speed: f64,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Yaml,
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -29,36 +100,467 @@ pub struct Payload {
     pub deployments: Vec<Deployment>,
 }
 
-#[derive(Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone)]
+impl Payload {
+    pub fn unhealthy_pods(&self, health: &HealthRules) -> Vec<&Pods> {
+        self.nodes
+            .values()
+            .flatten()
+            .filter(|pod| health.is_unhealthy(pod))
+            .collect()
+    }
+    // namespaces can be globs
+    pub fn only_namespaces(self, namespaces: &[String]) -> Payload {
+        let in_namespaces =
+            |namespace: &String| namespaces.iter().any(|ns| glob_match(ns, namespace));
+        Payload {
+            nodes: self
+                .nodes
+                .into_iter()
+                .map(|(node, pods)| {
+                    let pods = pods
+                        .into_iter()
+                        .filter(|pod| in_namespaces(&pod.namespace))
+                        .collect();
+                    (node, pods)
+                })
+                .collect(),
+            ingresses: self.ingresses.map(|ingresses| {
+                ingresses
+                    .into_iter()
+                    .filter(|ingress| in_namespaces(&ingress.namespace))
+                    .collect()
+            }),
+            timestamp: self.timestamp,
+            deployments: self
+                .deployments
+                .into_iter()
+                .filter(|deployment| in_namespaces(&deployment.namespace))
+                .collect(),
+        }
+    }
+    // when the simpledash server sent the payload, None if the timestamp is in a format we don't know
+    pub fn created_at(&self) -> Option<SystemTime> {
+        let timestamp = self.timestamp.trim();
+        if let Ok(time) = DateTime::parse_from_rfc3339(timestamp) {
+            return Some(time.into());
+        }
+        // go's default format, e.g 2023-10-18 12:00:00.123456 +0200 CEST m=+12.34
+        let date_time_offset = timestamp
+            .split_whitespace()
+            .take(3)
+            .collect::<Vec<&str>>()
+            .join(" ");
+        if let Ok(time) = DateTime::parse_from_str(&date_time_offset, "%Y-%m-%d %H:%M:%S%.f %z") {
+            return Some(time.into());
+        }
+        // without an offset the server is assumed to be in our timezone
+        let naive = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S%.f")
+            .or_else(|_| NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S%.f"))
+            .ok()?;
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|time| time.into())
+    }
+    // falls back to when we received the payload if the timestamp can't be parsed
+    pub fn age(&self, received_at: SystemTime, now: SystemTime) -> Duration {
+        now.duration_since(self.created_at().unwrap_or(received_at))
+            .unwrap_or_default() // clock skew can put the timestamp slightly in the future
+    }
+    // one summary per namespace in the cluster, in the same order, namespaces without pods included
+    pub fn namespace_summaries(
+        &self,
+        namespaces: &[String],
+        health: &HealthRules,
+    ) -> Vec<NamespaceSummary> {
+        let mut summaries = namespaces
+            .iter()
+            .map(|namespace| NamespaceSummary {
+                namespace: namespace.clone(),
+                ..Default::default()
+            })
+            .collect::<Vec<NamespaceSummary>>();
+        let index = namespaces
+            .iter()
+            .enumerate()
+            .map(|(i, namespace)| (namespace.as_str(), i))
+            .collect::<std::collections::HashMap<&str, usize>>();
+        let summary = |namespace: &str| index.get(namespace).copied();
+        for pod in self.nodes.values().flatten() {
+            if let Some(i) = summary(&pod.namespace) {
+                summaries[i].pods += 1;
+                if health.is_unhealthy(pod) {
+                    summaries[i].unhealthy_pods += 1;
+                }
+            }
+        }
+        for deployment in self.deployments.iter() {
+            if let Some(i) = summary(&deployment.namespace) {
+                summaries[i].deployments += 1;
+                if deployment.ready_replicas >= deployment.replicas {
+                    summaries[i].ready_deployments += 1;
+                }
+            }
+        }
+        for ingress in self.ingresses.iter().flatten() {
+            if let Some(i) = summary(&ingress.namespace) {
+                summaries[i].ingresses += 1;
+            }
+        }
+        summaries
+    }
+    pub fn total_pods(&self) -> usize {
+        self.nodes.values().map(|value| value.len()).sum::<usize>()
+    }
+}
+
+// a row in the namespace table on the start screen
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct NamespaceSummary {
+    pub namespace: String,
+    pub pods: usize,
+    pub unhealthy_pods: usize,
+    pub ready_deployments: usize,
+    pub deployments: usize,
+    pub ingresses: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct Pods {
-    #[serde(rename = "Namespace")]
+    #[serde(rename(deserialize = "Namespace"))]
     pub namespace: String,
-    #[serde(rename = "Name")]
+    #[serde(rename(deserialize = "Name"))]
     pub name: String,
-    #[serde(rename = "Image")]
+    #[serde(rename(deserialize = "Image"))]
     pub image: String,
-    #[serde(rename = "Status")]
-    pub status: String,
+    #[serde(rename(deserialize = "Status"))]
+    pub status: PodStatus,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Health {
+    Healthy,
+    Progressing,
+    Failing,
+}
+
+// The status column of `kubectl get pods` as sent by the simpledash server, statuses we
+// don't know about end up in `Unknown` and count as failing. The health rules in the
+// config file can override this classification.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[serde(from = "String", into = "String")]
+pub enum PodStatus {
+    Running,
+    Succeeded,
+    Completed,
+    Pending,
+    ContainerCreating,
+    PodInitializing,
+    Terminating,
+    Failed,
+    Error,
+    CrashLoopBackOff,
+    ImagePullBackOff,
+    ErrImagePull,
+    OOMKilled,
+    Unknown(String),
+}
+
+impl PodStatus {
+    pub fn health(&self) -> Health {
+        match self {
+            PodStatus::Running | PodStatus::Succeeded | PodStatus::Completed => Health::Healthy,
+            PodStatus::Pending
+            | PodStatus::ContainerCreating
+            | PodStatus::PodInitializing
+            | PodStatus::Terminating => Health::Progressing,
+            PodStatus::Failed
+            | PodStatus::Error
+            | PodStatus::CrashLoopBackOff
+            | PodStatus::ImagePullBackOff
+            | PodStatus::ErrImagePull
+            | PodStatus::OOMKilled
+            | PodStatus::Unknown(_) => Health::Failing,
+        }
+    }
+    pub fn as_str(&self) -> &str {
+        match self {
+            PodStatus::Running => "Running",
+            PodStatus::Succeeded => "Succeeded",
+            PodStatus::Completed => "Completed",
+            PodStatus::Pending => "Pending",
+            PodStatus::ContainerCreating => "ContainerCreating",
+            PodStatus::PodInitializing => "PodInitializing",
+            PodStatus::Terminating => "Terminating",
+            PodStatus::Failed => "Failed",
+            PodStatus::Error => "Error",
+            PodStatus::CrashLoopBackOff => "CrashLoopBackOff",
+            PodStatus::ImagePullBackOff => "ImagePullBackOff",
+            PodStatus::ErrImagePull => "ErrImagePull",
+            PodStatus::OOMKilled => "OOMKilled",
+            PodStatus::Unknown(status) => status,
+        }
+    }
+}
+
+impl From<String> for PodStatus {
+    fn from(status: String) -> PodStatus {
+        match status.as_str() {
+            "Running" => PodStatus::Running,
+            "Succeeded" => PodStatus::Succeeded,
+            "Completed" => PodStatus::Completed,
+            "Pending" => PodStatus::Pending,
+            "ContainerCreating" => PodStatus::ContainerCreating,
+            "PodInitializing" => PodStatus::PodInitializing,
+            "Terminating" => PodStatus::Terminating,
+            "Failed" => PodStatus::Failed,
+            "Error" => PodStatus::Error,
+            "CrashLoopBackOff" => PodStatus::CrashLoopBackOff,
+            "ImagePullBackOff" => PodStatus::ImagePullBackOff,
+            "ErrImagePull" => PodStatus::ErrImagePull,
+            "OOMKilled" => PodStatus::OOMKilled,
+            _ => PodStatus::Unknown(status),
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
+impl From<PodStatus> for String {
+    fn from(status: PodStatus) -> String {
+        status.as_str().to_string()
+    }
+}
+
+impl std::fmt::Display for PodStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Ingress {
-    #[serde(rename = "Endpoint")]
+    #[serde(rename(deserialize = "Endpoint"))]
     pub endpoint: String,
-    #[serde(rename = "Ip")]
+    #[serde(rename(deserialize = "Ip"))]
     pub ip: String,
-    #[serde(rename = "Namespace")]
+    #[serde(rename(deserialize = "Namespace"))]
     pub namespace: String,
 }
 
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Serialize, Default)]
 pub struct Deployment {
-    #[serde(rename = "Namespace")]
+    #[serde(rename(deserialize = "Namespace"))]
     pub namespace: String,
-    #[serde(rename = "Name")]
+    #[serde(rename(deserialize = "Name"))]
     pub name: String,
-    #[serde(rename = "Replicas")]
+    #[serde(rename(deserialize = "Replicas"))]
     pub replicas: u32,
-    #[serde(rename = "ReadyReplicas")]
+    #[serde(rename(deserialize = "ReadyReplicas"))]
     pub ready_replicas: u32,
 }
+
+// The documented `sc status --output json|yaml` schema. Fields are only ever added,
+// anything else bumps `schema_version`.
+#[derive(Debug, Serialize)]
+pub struct Snapshot<'a> {
+    pub schema_version: u32,
+    pub host: &'a str,
+    pub cluster_name: &'a str,
+    pub timestamp: &'a str,
+    pub status: &'static str,
+    pub total_pods: usize,
+    pub unhealthy_pods: Vec<&'a Pods>,
+    pub nodes: BTreeMap<&'a str, Vec<&'a Pods>>,
+    pub deployments: Vec<&'a Deployment>,
+    pub ingresses: Vec<&'a Ingress>,
+}
+
+impl<'a> Snapshot<'a> {
+    pub fn new(
+        host: &'a str,
+        cluster_info: &'a ClusterInfo,
+        payload: &'a Payload,
+        health: &HealthRules,
+    ) -> Snapshot<'a> {
+        // everything sorted so that two snapshots of the same cluster state are identical
+        let mut unhealthy_pods = payload.unhealthy_pods(health);
+        unhealthy_pods.sort();
+        let nodes = payload
+            .nodes
+            .iter()
+            .map(|(node, pods)| {
+                let mut pods = pods.iter().collect::<Vec<&Pods>>();
+                pods.sort();
+                (node.as_str(), pods)
+            })
+            .collect();
+        let mut deployments = payload.deployments.iter().collect::<Vec<&Deployment>>();
+        deployments.sort_by_key(|d| (&d.namespace, &d.name));
+        let mut ingresses = payload
+            .ingresses
+            .iter()
+            .flatten()
+            .collect::<Vec<&Ingress>>();
+        ingresses.sort_by_key(|i| (&i.namespace, &i.endpoint));
+        Snapshot {
+            schema_version: 1,
+            host,
+            cluster_name: &cluster_info.cluster_name,
+            timestamp: &payload.timestamp,
+            status: if unhealthy_pods.is_empty() {
+                "OK"
+            } else {
+                "BAD"
+            },
+            total_pods: payload.total_pods(),
+            unhealthy_pods,
+            nodes,
+            deployments,
+            ingresses,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_age_is_based_on_the_payload_timestamp() {
+        // Arrange
+        let payload = Payload {
+            timestamp: "2023-10-18 12:00:00.123456 +0200 CEST m=+12.345".to_string(),
+            ..Default::default()
+        };
+        let created_at = SystemTime::from(
+            chrono::DateTime::parse_from_rfc3339("2023-10-18T12:00:00.123456+02:00").unwrap(),
+        );
+        let now = created_at + Duration::from_secs(90);
+
+        // Act
+        let age = payload.age(SystemTime::UNIX_EPOCH, now);
+
+        // Assert
+        assert_eq!(age, Duration::from_secs(90));
+    }
+    #[test]
+    fn payload_age_falls_back_to_when_it_was_received() {
+        // Arrange
+        let payload = Payload {
+            timestamp: "yesterday".to_string(),
+            ..Default::default()
+        };
+        let received_at = SystemTime::now();
+
+        // Act
+        let age = payload.age(received_at, received_at + Duration::from_secs(5));
+
+        // Assert
+        assert_eq!(age, Duration::from_secs(5));
+    }
+    #[test]
+    fn pod_status_classifies_known_statuses_and_keeps_unknown_ones() {
+        // Arrange
+        let json = r#"[
+            {"Namespace": "ns", "Name": "a", "Image": "a:1", "Status": "Running"},
+            {"Namespace": "ns", "Name": "b", "Image": "b:1", "Status": "ContainerCreating"},
+            {"Namespace": "ns", "Name": "c", "Image": "c:1", "Status": "Init:CrashLoopBackOff"}
+        ]"#;
+
+        // Act
+        let pods: Vec<Pods> = serde_json::from_str(json).unwrap();
+
+        // Assert
+        assert_eq!(pods[0].status.health(), Health::Healthy);
+        assert_eq!(pods[1].status.health(), Health::Progressing);
+        assert_eq!(
+            pods[2].status,
+            PodStatus::Unknown("Init:CrashLoopBackOff".to_string())
+        );
+        assert_eq!(pods[2].status.health(), Health::Failing);
+        assert_eq!(
+            serde_json::to_value(&pods[2]).unwrap()["status"],
+            "Init:CrashLoopBackOff"
+        );
+    }
+    #[test]
+    fn namespace_summaries_count_pods_deployments_and_ingresses_per_namespace() {
+        // Arrange
+        let payload = Payload {
+            deployments: vec![
+                Deployment {
+                    namespace: "namespace1".to_string(),
+                    name: "api".to_string(),
+                    replicas: 2,
+                    ready_replicas: 2,
+                },
+                Deployment {
+                    namespace: "namespace1".to_string(),
+                    name: "worker".to_string(),
+                    replicas: 2,
+                    ready_replicas: 1,
+                },
+            ],
+            ingresses: Some(vec![Ingress {
+                endpoint: "api.mycompany.com".to_string(),
+                ip: "10.0.0.1".to_string(),
+                namespace: "namespace1".to_string(),
+            }]),
+            ..Default::default()
+        };
+        let namespaces = vec!["namespace1".to_string(), "namespace2".to_string()];
+
+        // Act
+        let summaries = payload.namespace_summaries(&namespaces, &HealthRules::default());
+
+        // Assert
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].deployments, 2);
+        assert_eq!(summaries[0].ready_deployments, 1);
+        assert_eq!(summaries[0].ingresses, 1);
+        assert_eq!(
+            summaries[1],
+            NamespaceSummary {
+                namespace: "namespace2".to_string(),
+                ..Default::default()
+            }
+        );
+    }
+    #[test]
+    fn only_namespaces_drops_everything_outside_the_namespaces() {
+        // Arrange
+        let payload = Payload {
+            nodes: std::collections::HashMap::from_iter(vec![(
+                "node1".to_string(),
+                vec![
+                    Pods {
+                        namespace: "namespace1".to_string(),
+                        name: "pod1".to_string(),
+                        status: PodStatus::Running,
+                        image: "image1:tag1".to_string(),
+                    },
+                    Pods {
+                        namespace: "namespace2".to_string(),
+                        name: "pod2".to_string(),
+                        status: PodStatus::CrashLoopBackOff,
+                        image: "image2:tag2".to_string(),
+                    },
+                ],
+            )]),
+            deployments: vec![Deployment {
+                name: "deployment2".to_string(),
+                namespace: "namespace2".to_string(),
+                ready_replicas: 0,
+                replicas: 1,
+            }],
+            ..Default::default()
+        };
+
+        // Act
+        let payload = payload.only_namespaces(&["namespace1".to_string()]);
+
+        // Assert
+        assert_eq!(payload.total_pods(), 1);
+        assert!(payload.unhealthy_pods(&HealthRules::default()).is_empty());
+        assert!(payload.deployments.is_empty());
+    }
+}
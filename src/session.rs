@@ -0,0 +1,160 @@
+use chrono::{DateTime, Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// a line of a recording made by sc record, the first one is the context of the cluster
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record {
+    Context {
+        host: String,
+        received_at: String,
+        // what /context returned, as is
+        context: String,
+    },
+    Frame {
+        received_at: String,
+        // the websocket frame, as is
+        frame: String,
+    },
+}
+
+pub struct Recorder {
+    path: PathBuf,
+    file: File,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, host: &str, context: &str) -> Result<Recorder, String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Error creating recording {}: {}", path.display(), e))?;
+        let mut recorder = Recorder {
+            path: path.to_path_buf(),
+            file,
+        };
+        recorder.write(&Record::Context {
+            host: host.to_string(),
+            received_at: format_time(SystemTime::now()),
+            context: context.to_string(),
+        })?;
+        Ok(recorder)
+    }
+    pub fn frame(&mut self, received_at: SystemTime, frame: &str) -> Result<(), String> {
+        self.write(&Record::Frame {
+            received_at: format_time(received_at),
+            frame: frame.to_string(),
+        })
+    }
+    fn write(&mut self, record: &Record) -> Result<(), String> {
+        let mut line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+        line.push('\n');
+        self.file
+            .write_all(line.as_bytes())
+            .map_err(|e| format!("Error writing recording {}: {}", self.path.display(), e))
+    }
+}
+
+pub struct Session {
+    pub host: String,
+    pub context: String,
+    pub frames: Vec<(SystemTime, String)>,
+}
+
+pub fn load(path: &Path) -> Result<Session, String> {
+    let file = File::open(path)
+        .map_err(|e| format!("Error opening recording {}: {}", path.display(), e))?;
+    let invalid = |line: usize, e: &dyn std::fmt::Display| {
+        format!(
+            "Error: invalid recording {} (line {}): {}",
+            path.display(),
+            line,
+            e
+        )
+    };
+    let mut session = None;
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| invalid(i + 1, &e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str::<Record>(&line).map_err(|e| invalid(i + 1, &e))?;
+        match (record, session.as_mut()) {
+            (Record::Context { host, context, .. }, None) => {
+                session = Some(Session {
+                    host,
+                    context,
+                    frames: Vec::new(),
+                })
+            }
+            (Record::Frame { received_at, frame }, Some(session)) => {
+                let received_at = parse_time(&received_at).map_err(|e| invalid(i + 1, &e))?;
+                session.frames.push((received_at, frame));
+            }
+            (Record::Context { .. }, Some(_)) => {
+                return Err(invalid(i + 1, &"more than one context"))
+            }
+            (Record::Frame { .. }, None) => {
+                return Err(invalid(i + 1, &"frame before the context"))
+            }
+        }
+    }
+    match session {
+        Some(session) if !session.frames.is_empty() => Ok(session),
+        _ => Err(format!(
+            "Error: {} has nothing to replay, it has no frames",
+            path.display()
+        )),
+    }
+}
+
+fn format_time(time: SystemTime) -> String {
+    DateTime::<Local>::from(time).to_rfc3339_opts(SecondsFormat::Millis, false)
+}
+
+fn parse_time(time: &str) -> Result<SystemTime, chrono::ParseError> {
+    Ok(DateTime::parse_from_rfc3339(time)?.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn a_recording_loads_with_its_context_and_frames() {
+        // Arrange
+        let path = std::env::temp_dir().join(format!("sc-session-{}.jsonl", std::process::id()));
+        let context = r#"{"ClusterName":"prod","Namespaces":["payments"],"DeploymentLogsLinkEnabled":false,"DeploymentLogsLink":""}"#;
+        let received_at = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_250);
+        let mut recorder = Recorder::create(&path, "https://host1", context).unwrap();
+        recorder.frame(received_at, r#"{"Nodes":{}}"#).unwrap();
+        recorder
+            .frame(
+                received_at + Duration::from_secs(2),
+                r#"{"Nodes":{"node1":[]}}"#,
+            )
+            .unwrap();
+
+        // Act
+        let session = load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        // Assert
+        let session = session.unwrap();
+        assert_eq!(session.host, "https://host1");
+        assert_eq!(session.context, context);
+        assert_eq!(
+            session.frames,
+            vec![
+                (received_at, r#"{"Nodes":{}}"#.to_string()),
+                (
+                    received_at + Duration::from_secs(2),
+                    r#"{"Nodes":{"node1":[]}}"#.to_string()
+                ),
+            ]
+        );
+    }
+}
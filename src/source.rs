@@ -0,0 +1,479 @@
+use crate::client::{Client, Connection};
+use crate::models::ClusterInfo;
+use crate::session;
+use crate::stream::{ConnectionState, StreamEvent};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::net::TcpStream;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use tungstenite::{stream::MaybeTlsStream, Message, WebSocket};
+use url::Url;
+
+// how often we check on an idle connection and how long the server gets to answer a ping
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+const PING_AFTER: Duration = Duration::from_secs(15);
+const PONG_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+// Where sc gets the cluster from: the simpledash server, a recording or a script in the tests.
+// The reconnecting and everything after it is the same for all of them.
+pub trait DataSource {
+    // the context as the server sent it
+    fn context(&mut self) -> Result<String, Box<dyn Error>>;
+    // (re)connects the stream of frames
+    fn connect(&mut self) -> Result<(), Box<dyn Error>>;
+    // the next frame (or a change of state), None if nothing arrived meanwhile.
+    // an error means the connection is lost and has to be reconnected
+    fn next(&mut self) -> Result<Option<StreamEvent<String>>, Box<dyn Error>>;
+
+    fn cluster_info(&mut self) -> Result<ClusterInfo, Box<dyn Error>> {
+        Ok(serde_json::from_str(&self.context()?)?)
+    }
+}
+
+// the simpledash server
+pub struct LiveSource {
+    host: Url,
+    client: Client,
+    socket: Option<Socket>,
+    last_activity: Instant,
+    ping_sent: Option<Instant>,
+    // the credentials were resolved for the first connect, reconnects get fresh ones
+    connected_before: bool,
+}
+
+impl LiveSource {
+    pub fn new(host: &Url, connection: &Connection) -> Result<LiveSource, Box<dyn Error>> {
+        Ok(LiveSource {
+            host: host.clone(),
+            client: Client::new(connection)?,
+            socket: None,
+            last_activity: Instant::now(),
+            ping_sent: None,
+            connected_before: false,
+        })
+    }
+}
+
+impl DataSource for LiveSource {
+    fn context(&mut self) -> Result<String, Box<dyn Error>> {
+        self.client.get_context(&self.host)
+    }
+    fn connect(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.connected_before {
+            self.client.refresh_auth()?;
+        }
+        self.connected_before = true;
+        let socket = self.client.connect_to_host(&self.host)?;
+        let _ = set_read_timeout(&socket, Some(READ_TIMEOUT));
+        self.socket = Some(socket);
+        self.last_activity = Instant::now();
+        self.ping_sent = None;
+        Ok(())
+    }
+    fn next(&mut self) -> Result<Option<StreamEvent<String>>, Box<dyn Error>> {
+        let socket = self.socket.as_mut().ok_or("not connected")?;
+        let result = receive_frame(socket);
+        match result {
+            // a half-open connection never errors, so an unanswered ping is the only way to notice it
+            Err(_) if is_timeout(&result) => {
+                if self.ping_sent.is_none() && self.last_activity.elapsed() >= PING_AFTER {
+                    let _ = socket.send(Message::Ping(Vec::new()));
+                    self.ping_sent = Some(Instant::now());
+                }
+                match self.ping_sent {
+                    Some(sent) if sent.elapsed() >= PONG_TIMEOUT => Err("no answer to ping".into()),
+                    _ => Ok(None),
+                }
+            }
+            // the socket is unusable after any websocket error, reading again would just fail again
+            Err(e) => Err(e),
+            Ok(frame) => {
+                self.last_activity = Instant::now(); // any frame, including a pong, proves the connection is alive
+                self.ping_sent = None;
+                Ok(frame.map(StreamEvent::Payload))
+            }
+        }
+    }
+}
+
+fn set_read_timeout(socket: &Socket, timeout: Option<Duration>) -> std::io::Result<()> {
+    match socket.get_ref() {
+        MaybeTlsStream::Plain(stream) => stream.set_read_timeout(timeout),
+        MaybeTlsStream::NativeTls(stream) => stream.get_ref().set_read_timeout(timeout),
+        _ => Ok(()),
+    }
+}
+
+fn is_timeout<T>(result: &Result<T, Box<dyn Error>>) -> bool {
+    match result {
+        Err(e) => matches!(
+            e.downcast_ref::<tungstenite::Error>(),
+            Some(tungstenite::Error::Io(io)) if matches!(
+                io.kind(),
+                std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+            )
+        ),
+        Ok(_) => false,
+    }
+}
+
+fn receive_frame(socket: &mut Socket) -> Result<Option<String>, Box<dyn Error>> {
+    let read_result = socket.read()?;
+
+    if !read_result.is_empty() {
+        Ok(Some(read_result.to_string()))
+    } else {
+        Ok(None) // no payload on ping (tungstenite replies with pong automatically)
+    }
+}
+
+// a recording made by sc record, with the same pauses between the frames as when they were
+// recorded, divided by speed
+pub struct RecordingSource {
+    pub host: String,
+    context: String,
+    frames: VecDeque<(SystemTime, String)>,
+    speed: f64,
+    previous: Option<SystemTime>,
+    // the replay state that goes with the frame that was just returned
+    pending: Option<StreamEvent<String>>,
+    finished: bool,
+}
+
+impl RecordingSource {
+    pub fn load(path: &Path, speed: f64) -> Result<RecordingSource, String> {
+        let session = session::load(path)?;
+        Ok(RecordingSource {
+            host: session.host,
+            context: session.context,
+            frames: session.frames.into(),
+            speed,
+            previous: None,
+            pending: None,
+            finished: false,
+        })
+    }
+}
+
+impl DataSource for RecordingSource {
+    fn context(&mut self) -> Result<String, Box<dyn Error>> {
+        Ok(self.context.clone())
+    }
+    fn connect(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(()) // a recording never drops
+    }
+    fn next(&mut self) -> Result<Option<StreamEvent<String>>, Box<dyn Error>> {
+        if let Some(event) = self.pending.take() {
+            return Ok(Some(event));
+        }
+        let Some((recorded_at, frame)) = self.frames.pop_front() else {
+            return match (self.previous, self.finished) {
+                (Some(recorded_at), false) => {
+                    self.finished = true;
+                    let finished = ConnectionState::ReplayFinished { recorded_at };
+                    Ok(Some(StreamEvent::State(finished)))
+                }
+                // the dashboard keeps showing the last payload until it is closed
+                _ => {
+                    thread::sleep(READ_TIMEOUT);
+                    Ok(None)
+                }
+            };
+        };
+        if let Some(previous) = self.previous {
+            let pause = recorded_at.duration_since(previous).unwrap_or_default();
+            thread::sleep(pause.div_f64(self.speed));
+        }
+        self.previous = Some(recorded_at);
+        let speed = self.speed;
+        self.pending = Some(StreamEvent::State(ConnectionState::Replaying {
+            recorded_at,
+            speed,
+        }));
+        Ok(Some(StreamEvent::Payload(frame)))
+    }
+}
+
+// scripted in the tests (also of the sc binary, which is why it isn't cfg(test)), each step is
+// what the next call to connect or next does
+pub struct FakeSource {
+    pub context: String,
+    pub steps: VecDeque<Step>,
+}
+
+pub enum Step {
+    Frame(String),
+    // the connection is lost
+    Drop,
+    // the next attempt to connect fails
+    Refuse,
+}
+
+impl FakeSource {
+    pub fn new(context: &str, steps: Vec<Step>) -> FakeSource {
+        FakeSource {
+            context: context.to_string(),
+            steps: steps.into(),
+        }
+    }
+}
+
+impl DataSource for FakeSource {
+    fn context(&mut self) -> Result<String, Box<dyn Error>> {
+        Ok(self.context.clone())
+    }
+    fn connect(&mut self) -> Result<(), Box<dyn Error>> {
+        match self.steps.front() {
+            Some(Step::Refuse) => {
+                self.steps.pop_front();
+                Err("connection refused".into())
+            }
+            Some(_) => Ok(()),
+            None => Err("end of script".into()),
+        }
+    }
+    fn next(&mut self) -> Result<Option<StreamEvent<String>>, Box<dyn Error>> {
+        match self.steps.pop_front() {
+            Some(Step::Frame(frame)) => Ok(Some(StreamEvent::Payload(frame))),
+            Some(Step::Drop) => Err("connection lost".into()),
+            // left for connect to refuse
+            Some(Step::Refuse) => {
+                self.steps.push_front(Step::Refuse);
+                Err("connection lost".into())
+            }
+            None => Err("end of script".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::AuthConfig;
+    use crate::mock_server::{self, MockServer, Scenario};
+    use crate::stream::{first_payload, receive_payloads, ReconnectPolicy};
+    use crate::tls::TlsConfig;
+    use std::cell::RefCell;
+
+    #[test]
+    fn a_recording_replays_its_frames_and_then_says_it_is_finished() {
+        // Arrange
+        let recorded_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut source = RecordingSource {
+            host: "https://host1".to_string(),
+            context: "{}".to_string(),
+            frames: [
+                (recorded_at, "first".to_string()),
+                (recorded_at + Duration::from_secs(1), "second".to_string()),
+            ]
+            .into(),
+            speed: 100.0,
+            previous: None,
+            pending: None,
+            finished: false,
+        };
+
+        // Act
+        let started = Instant::now();
+        let events = (0..5)
+            .map(|_| match source.next().unwrap() {
+                Some(StreamEvent::Payload(frame)) => frame,
+                Some(StreamEvent::State(ConnectionState::Replaying {
+                    recorded_at: at, ..
+                })) => {
+                    format!(
+                        "replaying {}s",
+                        at.duration_since(recorded_at).unwrap().as_secs()
+                    )
+                }
+                Some(StreamEvent::State(ConnectionState::ReplayFinished { .. })) => {
+                    "finished".to_string()
+                }
+                _ => "other".to_string(),
+            })
+            .collect::<Vec<String>>();
+
+        // Assert
+        assert_eq!(
+            events,
+            vec![
+                "first",
+                "replaying 0s",
+                "second",
+                "replaying 1s",
+                "finished"
+            ]
+        );
+        // a second between the frames at 100x
+        assert!(started.elapsed() >= Duration::from_millis(10));
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+
+    fn node(name: &str) -> String {
+        format!(
+            r#"{{"Nodes":{{"{}":[]}},"Ingresses":null,"Timestamp":"","Deployments":[]}}"#,
+            name
+        )
+    }
+
+    #[test]
+    fn live_source_authenticates_and_reconnects_to_a_dropped_server() {
+        // Arrange
+        let server = MockServer::start(
+            "127.0.0.1:0",
+            Scenario {
+                context: r#"{"ClusterName":"mock","Namespaces":["payments"],"DeploymentLogsLinkEnabled":false,"DeploymentLogsLink":""}"#.to_string(),
+                steps: vec![
+                    mock_server::Step::Send(node("first")),
+                    mock_server::Step::Drop,
+                    mock_server::Step::Send(node("second")),
+                ],
+                repeat: false,
+                auth: Some("Bearer secret".to_string()),
+            },
+        )
+        .unwrap();
+        let connection = Connection {
+            auth: AuthConfig {
+                token: Some("secret".to_string()),
+                ..Default::default()
+            }
+            .resolve()
+            .unwrap(),
+            ..Default::default()
+        };
+        // under a base path, like a simpledash behind a path based ingress
+        let host = Url::parse(&format!("{}/simpledash/?team=payments", server.url())).unwrap();
+        let mut source = LiveSource::new(&host, &connection).unwrap();
+        let mut unauthorized = LiveSource::new(&host, &Connection::default()).unwrap();
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(10),
+            max_attempts: Some(5),
+        };
+        let events = RefCell::new(Vec::new());
+
+        // Act
+        let unauthorized = (
+            unauthorized.cluster_info().is_err(),
+            unauthorized.connect().is_err(),
+        );
+        let cluster_info = source.cluster_info().unwrap();
+        source.connect().unwrap();
+        receive_payloads(&mut source, &policy, |event| {
+            let event = match event {
+                StreamEvent::Payload(payload) => payload.nodes.into_keys().collect(),
+                StreamEvent::State(ConnectionState::Reconnecting { .. }) => {
+                    "reconnecting".to_string()
+                }
+                StreamEvent::State(state) => format!("{:?}", state),
+                StreamEvent::Error(e) => e,
+            };
+            let done = event == "second";
+            events.borrow_mut().push(event);
+            !done
+        });
+
+        // Assert
+        assert_eq!(unauthorized, (true, true));
+        assert_eq!(cluster_info.cluster_name, "mock");
+        assert_eq!(
+            events.into_inner(),
+            vec!["first", "reconnecting", "Connected", "second"]
+        );
+    }
+
+    #[test]
+    fn live_source_runs_the_token_command_again_to_reconnect() {
+        // Arrange
+        let server = MockServer::start(
+            "127.0.0.1:0",
+            Scenario {
+                auth: Some("Bearer fresh".to_string()),
+                ..Scenario::demo()
+            },
+        )
+        .unwrap();
+        let token = std::env::temp_dir().join(format!("sc-token-{}", std::process::id()));
+        std::fs::write(&token, "expired").unwrap();
+        let connection = Connection {
+            auth: AuthConfig {
+                token_command: Some(format!("cat {}", token.display())),
+                ..Default::default()
+            }
+            .resolve()
+            .unwrap(),
+            ..Default::default()
+        };
+        let mut source = LiveSource::new(&Url::parse(&server.url()).unwrap(), &connection).unwrap();
+
+        // Act
+        let expired = source.connect().is_err();
+        std::fs::write(&token, "fresh").unwrap();
+        let reconnected = source.connect().is_ok();
+        std::fs::remove_file(&token).unwrap();
+
+        // Assert
+        assert!(expired);
+        assert!(reconnected);
+    }
+
+    #[test]
+    fn live_source_trusts_the_given_ca_for_the_context_and_the_websocket() {
+        // Arrange
+        let testdata = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata");
+        let read = |file: &str| std::fs::read(testdata.join(file)).unwrap();
+        let scenario = Scenario {
+            context: r#"{"ClusterName":"private","Namespaces":[],"DeploymentLogsLinkEnabled":false,"DeploymentLogsLink":""}"#.to_string(),
+            steps: vec![mock_server::Step::Send(node("node1"))],
+            repeat: true,
+            auth: None,
+        };
+        let server = MockServer::start_tls(
+            "127.0.0.1:0",
+            scenario,
+            &read("server.pem"),
+            &read("server-key.pem"),
+        )
+        .unwrap();
+        let tls = |config: TlsConfig| {
+            let connection = Connection {
+                tls: config.load().unwrap(),
+                ..Default::default()
+            };
+            LiveSource::new(&Url::parse(&server.url()).unwrap(), &connection).unwrap()
+        };
+        let mut trusted = tls(TlsConfig {
+            cacert: Some(testdata.join("ca.pem")),
+            ..Default::default()
+        });
+        let mut untrusted = tls(TlsConfig::default());
+        let mut insecure = tls(TlsConfig {
+            insecure: true,
+            ..Default::default()
+        });
+
+        // Act
+        let trusted = (
+            trusted.cluster_info().map(|info| info.cluster_name).ok(),
+            trusted
+                .connect()
+                .and_then(|_| first_payload(&mut trusted))
+                .is_ok(),
+        );
+        let untrusted = (
+            untrusted.cluster_info().is_err(),
+            untrusted.connect().is_err(),
+        );
+        let insecure = (insecure.cluster_info().is_ok(), insecure.connect().is_ok());
+
+        // Assert
+        assert_eq!(trusted, (Some("private".to_string()), true));
+        assert_eq!(untrusted, (true, true));
+        assert_eq!(insecure, (true, true));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::glob::is_glob;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const MAX_RECENT: usize = 10;
+
+// what sc remembers between runs, per host
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct State {
+    #[serde(default)]
+    pub hosts: BTreeMap<String, HostState>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct HostState {
+    // most recent first
+    #[serde(default)]
+    pub recent: Vec<String>,
+    #[serde(default)]
+    pub favourites: Vec<String>,
+    // the namespaces (or globs) watched together last time, for --last
+    #[serde(default)]
+    pub last: Vec<String>,
+}
+
+impl State {
+    pub fn host(&self, host: &str) -> HostState {
+        self.hosts.get(host).cloned().unwrap_or_default()
+    }
+    pub fn visit(&mut self, host: &str, namespaces: &[String]) {
+        let host = self.hosts.entry(host.to_string()).or_default();
+        host.last = namespaces.to_vec();
+        // globs are only remembered in last, the namespace picker lists namespaces
+        for namespace in namespaces.iter().rev().filter(|ns| !is_glob(ns)) {
+            host.recent.retain(|ns| ns != namespace);
+            host.recent.insert(0, namespace.clone());
+        }
+        host.recent.truncate(MAX_RECENT);
+    }
+    pub fn toggle_favourite(&mut self, host: &str, namespace: &str) {
+        let favourites = &mut self.hosts.entry(host.to_string()).or_default().favourites;
+        match favourites.iter().position(|ns| ns == namespace) {
+            Some(i) => {
+                favourites.remove(i);
+            }
+            None => favourites.push(namespace.to_string()),
+        }
+    }
+}
+
+// $XDG_STATE_HOME/simpledash/state.toml, falling back to ~/.local/state/simpledash/state.toml
+pub fn default_path() -> Option<PathBuf> {
+    let state_home = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?)
+            .join(".local")
+            .join("state"),
+    };
+    Some(state_home.join("simpledash").join("state.toml"))
+}
+
+// the state is just a convenience, a missing or broken file starts over instead of failing
+pub fn load() -> State {
+    default_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| toml::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+// reloads before changing so that another sc running at the same time doesn't get overwritten
+pub fn update(change: impl FnOnce(&mut State)) -> Result<State, String> {
+    let mut state = load();
+    change(&mut state);
+    let path = default_path().ok_or("Error saving state: no home directory")?;
+    let content = toml::to_string(&state).map_err(|e| format!("Error saving state: {}", e))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Error saving state to {}: {}", path.display(), e))?;
+    }
+    std::fs::write(&path, content)
+        .map_err(|e| format!("Error saving state to {}: {}", path.display(), e))?;
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visit_keeps_the_most_recent_namespaces_first_without_duplicates() {
+        // Arrange
+        let mut state = State::default();
+
+        // Act
+        for namespaces in [vec!["a"], vec!["b"], vec!["a"], vec!["c", "team-*"]] {
+            let namespaces = namespaces.into_iter().map(String::from).collect::<Vec<_>>();
+            state.visit("https://host1", &namespaces);
+        }
+        state.toggle_favourite("https://host1", "b");
+        state.toggle_favourite("https://host1", "c");
+        state.toggle_favourite("https://host1", "b");
+
+        // Assert
+        assert_eq!(state.host("https://host1").recent, vec!["c", "a", "b"]);
+        assert_eq!(state.host("https://host1").favourites, vec!["c"]);
+        assert_eq!(state.host("https://host1").last, vec!["c", "team-*"]);
+        assert_eq!(state.host("https://host2"), HostState::default());
+    }
+}
@@ -0,0 +1,295 @@
+use crate::models::Payload;
+use crate::source::DataSource;
+use chrono::{DateTime, Local};
+use std::fmt;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// payloads are parsed, except when recording, which keeps the raw frames
+pub enum StreamEvent<T = Payload> {
+    Payload(T),
+    State(ConnectionState),
+    Error(String),
+}
+
+impl StreamEvent<String> {
+    pub fn parse(self) -> StreamEvent {
+        match self {
+            StreamEvent::Payload(frame) => match serde_json::from_str(&frame) {
+                Ok(payload) => StreamEvent::Payload(payload),
+                Err(e) => StreamEvent::Error(format!("Error receiving payload: {}", e)),
+            },
+            StreamEvent::State(state) => StreamEvent::State(state),
+            StreamEvent::Error(error) => StreamEvent::Error(error),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConnectionState {
+    Connected,
+    // why the connection was lost, or why the last attempt failed
+    Reconnecting {
+        attempt: u32,
+        delay: Duration,
+        offline_since: SystemTime,
+        reason: String,
+    },
+    GaveUp {
+        attempts: u32,
+        offline_since: SystemTime,
+        reason: String,
+    },
+    // sc replay, with when the current payload was recorded
+    Replaying {
+        recorded_at: SystemTime,
+        speed: f64,
+    },
+    ReplayFinished {
+        recorded_at: SystemTime,
+    },
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let since = |time: &SystemTime| DateTime::<Local>::from(*time).format("%H:%M:%S");
+        let recorded =
+            |time: &SystemTime| DateTime::<Local>::from(*time).format("%Y-%m-%d %H:%M:%S");
+        match self {
+            ConnectionState::Connected => write!(f, "connected"),
+            ConnectionState::Reconnecting {
+                attempt,
+                delay,
+                offline_since,
+                reason,
+            } => write!(
+                f,
+                "offline since {}, reconnect attempt {} in {}s ({})",
+                since(offline_since),
+                attempt,
+                delay.as_secs_f32().ceil(),
+                reason
+            ),
+            ConnectionState::GaveUp {
+                attempts,
+                offline_since,
+                reason,
+            } => write!(
+                f,
+                "offline since {}, gave up after {} reconnect attempts ({})",
+                since(offline_since),
+                attempts,
+                reason
+            ),
+            ConnectionState::Replaying { recorded_at, speed } => {
+                write!(f, "replaying {} at {}x", recorded(recorded_at), speed)
+            }
+            ConnectionState::ReplayFinished { recorded_at } => {
+                write!(f, "end of recording, last update {}", recorded(recorded_at))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ReconnectPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub max_attempts: Option<u32>,
+}
+
+impl ReconnectPolicy {
+    pub fn new(max_attempts: Option<u32>) -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            max_attempts,
+        }
+    }
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .initial_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        // half of the delay is random so that clients dropped at the same time don't reconnect in lockstep
+        exponential / 2 + (exponential / 2).mul_f64(rand::random::<f64>())
+    }
+}
+
+// the source keeps streaming in a background thread, reconnecting as needed
+pub fn spawn_payload_receiver(
+    mut source: impl DataSource + Send + 'static,
+    policy: ReconnectPolicy,
+) -> Receiver<StreamEvent> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || receive_payloads(&mut source, &policy, |event| tx.send(event).is_ok()));
+    rx
+}
+
+// forwards payloads, errors and connection state changes until send returns false or reconnecting is given up
+pub fn receive_payloads(
+    source: &mut impl DataSource,
+    policy: &ReconnectPolicy,
+    send: impl Fn(StreamEvent) -> bool,
+) {
+    receive_frames(source, policy, |event| send(event.parse()))
+}
+
+// like receive_payloads, but with the frames as the server sent them
+pub fn receive_frames(
+    source: &mut impl DataSource,
+    policy: &ReconnectPolicy,
+    send: impl Fn(StreamEvent<String>) -> bool,
+) {
+    loop {
+        let event = match source.next() {
+            Ok(Some(event)) => event,
+            Ok(None) => continue, // nothing new, e.g a ping or pong
+            Err(e) if reconnect(source, policy, &send, e.to_string()) => continue,
+            Err(_) => return,
+        };
+        if !send(event) {
+            return; // receiving end is gone
+        }
+    }
+}
+
+// retries with exponential backoff until connected, false if the policy gives up or nobody is listening anymore.
+// reason is why the connection was lost, afterwards it's why the last attempt failed
+pub fn reconnect<T>(
+    source: &mut impl DataSource,
+    policy: &ReconnectPolicy,
+    send: &impl Fn(StreamEvent<T>) -> bool,
+    mut reason: String,
+) -> bool {
+    let offline_since = SystemTime::now();
+    let mut attempt = 1;
+    loop {
+        if policy.max_attempts.is_some_and(|max| attempt > max) {
+            send(StreamEvent::State(ConnectionState::GaveUp {
+                attempts: attempt - 1,
+                offline_since,
+                reason,
+            }));
+            return false;
+        }
+        let delay = policy.delay(attempt);
+        let reconnecting = ConnectionState::Reconnecting {
+            attempt,
+            delay,
+            offline_since,
+            reason: reason.clone(),
+        };
+        if !send(StreamEvent::State(reconnecting)) {
+            return false;
+        }
+        thread::sleep(delay);
+        match source.connect() {
+            Ok(()) => return send(StreamEvent::State(ConnectionState::Connected)),
+            Err(e) => reason = e.to_string(),
+        }
+        attempt += 1;
+    }
+}
+
+// skips everything up to the first payload, without reconnecting
+pub fn first_payload(source: &mut impl DataSource) -> Result<Payload, Box<dyn std::error::Error>> {
+    loop {
+        if let Some(StreamEvent::Payload(frame)) = source.next()? {
+            return Ok(serde_json::from_str(&frame)?);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::{FakeSource, Step};
+    use std::cell::RefCell;
+
+    fn frame(timestamp: &str) -> Step {
+        Step::Frame(format!(
+            r#"{{"Nodes":{{}},"Ingresses":null,"Timestamp":"{}","Deployments":[]}}"#,
+            timestamp
+        ))
+    }
+
+    #[test]
+    fn receive_payloads_reconnects_until_the_policy_gives_up() {
+        // Arrange
+        let mut source = FakeSource::new(
+            "{}",
+            vec![
+                frame("first"),
+                Step::Drop,
+                Step::Refuse,
+                frame("second"),
+                Step::Frame("not json".to_string()),
+            ],
+        );
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+            max_attempts: Some(2),
+        };
+        let events = RefCell::new(Vec::new());
+
+        // Act
+        receive_payloads(&mut source, &policy, |event| {
+            events.borrow_mut().push(match event {
+                StreamEvent::Payload(payload) => payload.timestamp,
+                StreamEvent::Error(_) => "error".to_string(),
+                StreamEvent::State(ConnectionState::Reconnecting {
+                    attempt, reason, ..
+                }) => format!("reconnecting {} ({})", attempt, reason),
+                StreamEvent::State(ConnectionState::GaveUp { reason, .. }) => {
+                    format!("gave up ({})", reason)
+                }
+                StreamEvent::State(state) => format!("{:?}", state),
+            });
+            true
+        });
+
+        // Assert
+        let events = events.into_inner();
+        assert_eq!(
+            events[..6],
+            [
+                "first",
+                "reconnecting 1 (connection lost)",
+                "reconnecting 2 (connection refused)",
+                "Connected",
+                "second",
+                "error",
+            ]
+        );
+        // the script is over, so the fake refuses to connect from here on
+        assert_eq!(
+            events[6..],
+            [
+                "reconnecting 1 (end of script)",
+                "reconnecting 2 (end of script)",
+                "gave up (end of script)",
+            ]
+        );
+    }
+
+    #[test]
+    fn reconnect_delay_grows_exponentially_with_jitter_up_to_the_cap() {
+        // Arrange
+        let policy = ReconnectPolicy::new(None);
+
+        for _ in 0..100 {
+            // Act
+            let first = policy.delay(1);
+            let fourth = policy.delay(4);
+            let hundredth = policy.delay(100);
+
+            // Assert
+            assert!(first >= Duration::from_millis(500) && first <= Duration::from_secs(1));
+            assert!(fourth >= Duration::from_secs(4) && fourth <= Duration::from_secs(8));
+            assert!(hundredth >= Duration::from_secs(30) && hundredth <= Duration::from_secs(60));
+        }
+    }
+}
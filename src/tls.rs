@@ -0,0 +1,160 @@
+use crate::config;
+use native_tls::{Certificate, Identity, TlsConnector};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+// TLS options for servers with a private CA or mTLS, from the flags or a `tls` section in the
+// config file. The same roots and client certificate are used for `/context` and the websocket.
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    // a pem bundle, trusted on top of the system roots
+    pub cacert: Option<PathBuf>,
+    // client certificate and its pkcs8 key, both pem
+    pub cert: Option<PathBuf>,
+    pub key: Option<PathBuf>,
+    #[serde(default)]
+    pub insecure: bool,
+}
+
+// The files of a `TlsConfig`, read and checked.
+#[derive(Default, Clone, PartialEq)]
+pub struct Tls {
+    ca_certs: Vec<Vec<u8>>,
+    identity: Option<(Vec<u8>, Vec<u8>)>,
+    pub insecure: bool,
+}
+
+// the key is a secret
+impl std::fmt::Debug for Tls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tls")
+            .field("ca_certs", &self.ca_certs.len())
+            .field("client_certificate", &self.identity.is_some())
+            .field("insecure", &self.insecure)
+            .finish()
+    }
+}
+
+impl TlsConfig {
+    // each option from us unless we don't have it
+    pub fn or(self, fallback: TlsConfig) -> TlsConfig {
+        TlsConfig {
+            cacert: self.cacert.or(fallback.cacert),
+            cert: self.cert.or(fallback.cert),
+            key: self.key.or(fallback.key),
+            insecure: self.insecure || fallback.insecure,
+        }
+    }
+    pub fn load(&self) -> Result<Tls, String> {
+        let read = |path: &Path| {
+            std::fs::read(config::expand_home(path))
+                .map_err(|e| format!("Error reading {}: {}", path.display(), e))
+        };
+        let ca_certs = match &self.cacert {
+            Some(path) => {
+                let certs = pem_certificates(&read(path)?);
+                if certs.is_empty() {
+                    return Err(format!("Error: no certificates in {}", path.display()));
+                }
+                certs
+            }
+            None => Vec::new(),
+        };
+        let identity = match (&self.cert, &self.key) {
+            (Some(cert), Some(key)) => Some((read(cert)?, read(key)?)),
+            (None, None) => None,
+            _ => return Err("Error: --cert and --key have to be given together".to_string()),
+        };
+        let tls = Tls {
+            ca_certs,
+            identity,
+            insecure: self.insecure,
+        };
+        // broken files are reported now rather than on every reconnect
+        tls.connector()
+            .map_err(|e| format!("Error: invalid certificate or key: {}", e))?;
+        Ok(tls)
+    }
+}
+
+impl Tls {
+    pub fn connector(&self) -> Result<TlsConnector, native_tls::Error> {
+        let mut builder = TlsConnector::builder();
+        for cert in self.ca_certs.iter() {
+            builder.add_root_certificate(Certificate::from_pem(cert)?);
+        }
+        if let Some((cert, key)) = &self.identity {
+            builder.identity(Identity::from_pkcs8(cert, key)?);
+        }
+        builder
+            .danger_accept_invalid_certs(self.insecure)
+            .danger_accept_invalid_hostnames(self.insecure)
+            .build()
+    }
+}
+
+// Certificate::from_pem only takes the first certificate of a bundle
+fn pem_certificates(pem: &[u8]) -> Vec<Vec<u8>> {
+    const END: &str = "-----END CERTIFICATE-----";
+    let pem = String::from_utf8_lossy(pem);
+    let mut certs = Vec::new();
+    let mut rest = pem.as_ref();
+    while let Some(start) = rest.find("-----BEGIN CERTIFICATE-----") {
+        let Some(end) = rest[start..].find(END) else {
+            break;
+        };
+        let end = start + end + END.len();
+        certs.push(rest.as_bytes()[start..end].to_vec());
+        rest = &rest[end..];
+    }
+    certs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testdata(file: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join(file)
+    }
+
+    #[test]
+    fn load_reads_every_certificate_of_a_bundle_and_checks_the_client_certificate() {
+        // Arrange
+        let bundle = std::env::temp_dir().join(format!("sc-bundle-{}.pem", std::process::id()));
+        let ca = std::fs::read_to_string(testdata("ca.pem")).unwrap();
+        let server = std::fs::read_to_string(testdata("server.pem")).unwrap();
+        std::fs::write(&bundle, format!("# private ca\n{}{}", ca, server)).unwrap();
+        let client = TlsConfig {
+            cacert: Some(bundle.clone()),
+            cert: Some(testdata("client.pem")),
+            key: Some(testdata("client-key.pem")),
+            insecure: false,
+        };
+        let mismatched = TlsConfig {
+            cert: Some(testdata("client.pem")),
+            key: Some(testdata("server-key.pem")),
+            ..Default::default()
+        };
+        let key_without_cert = TlsConfig {
+            key: Some(testdata("client-key.pem")),
+            ..Default::default()
+        };
+
+        // Act
+        let client = client.load();
+        let mismatched = mismatched.load();
+        let key_without_cert = key_without_cert.load();
+        std::fs::remove_file(&bundle).unwrap();
+
+        // Assert
+        let client = client.unwrap();
+        assert_eq!(client.ca_certs.len(), 2);
+        assert!(client.identity.is_some());
+        assert!(mismatched.is_err());
+        assert!(key_without_cert.is_err());
+    }
+}
@@ -0,0 +1,1670 @@
+use crate::cli::make_link;
+use crate::diff::{self, Change};
+use crate::glob;
+use crate::health::HealthRules;
+use crate::models::{ClusterInfo, Health, NamespaceSummary, Payload, Pods, Settings, Theme};
+use crate::state::HostState;
+use crate::stream::ConnectionState;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue, style::Print};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Row, Table, TableState},
+    Frame, Terminal,
+};
+use std::io::{self, Stdout, Write};
+use std::time::{Duration, SystemTime};
+
+pub enum Input {
+    SwitchNamespace,
+    Scroll(isize),
+    Select,
+    Back,
+    Sort(isize),
+    ReverseSort,
+    Type(char),
+    Erase,
+    Mark,
+    ToggleFavourite,
+    ToggleHistory,
+    Resize,
+    Quit,
+}
+
+pub struct Dashboard {
+    // namespaces and globs, in the order they were given
+    pub namespaces: Vec<String>,
+    pub error: Option<String>,
+    pub connection: ConnectionState,
+    pub received_at: SystemTime,
+    pub remembered: HostState,
+    stale_after: Duration,
+    highlight_for: Duration,
+    // --insecure, the status bar keeps reminding
+    insecure: bool,
+    // changes between payloads, oldest first
+    events: Vec<diff::Event>,
+    // the full event timeline, shown instead of the dashboard
+    history: Option<TableState>,
+    pods: TableState,
+    palette: Palette,
+    health: HealthRules,
+    endpoint_link: String,
+    picker: Option<NamespacePicker>,
+}
+
+const MAX_EVENTS: usize = 1000;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum SortBy {
+    Namespace,
+    Pods,
+    Unhealthy,
+    Deployments,
+    Ingresses,
+}
+
+const SORT_COLUMNS: [SortBy; 5] = [
+    SortBy::Namespace,
+    SortBy::Pods,
+    SortBy::Unhealthy,
+    SortBy::Deployments,
+    SortBy::Ingresses,
+];
+
+// the namespace table, shown instead of the dashboard while picking a namespace
+struct NamespacePicker {
+    sort: SortBy,
+    descending: bool,
+    filter: String,
+    // namespaces to watch together, the selected one is watched if none are marked
+    marked: Vec<String>,
+    // tracked by name so that the selection stays put when live updates reorder the rows
+    selected: Option<String>,
+    shown: Vec<String>,
+    table: TableState,
+}
+
+impl NamespacePicker {
+    fn new(selected: Option<String>, marked: Vec<String>) -> NamespacePicker {
+        NamespacePicker {
+            sort: SortBy::Unhealthy,
+            descending: true,
+            filter: String::new(),
+            marked,
+            selected,
+            shown: Vec::new(),
+            table: TableState::default(),
+        }
+    }
+    fn sort(&self, summaries: &mut [NamespaceSummary]) {
+        summaries.sort_by(|a, b| {
+            let by_column = match self.sort {
+                SortBy::Namespace => a.namespace.cmp(&b.namespace),
+                SortBy::Pods => a.pods.cmp(&b.pods),
+                SortBy::Unhealthy => a.unhealthy_pods.cmp(&b.unhealthy_pods),
+                SortBy::Deployments => (a.deployments - a.ready_deployments)
+                    .cmp(&(b.deployments - b.ready_deployments)),
+                SortBy::Ingresses => a.ingresses.cmp(&b.ingresses),
+            };
+            let by_column = if self.descending {
+                by_column.reverse()
+            } else {
+                by_column
+            };
+            by_column.then_with(|| a.namespace.cmp(&b.namespace))
+        });
+    }
+    // the namespaces matching the filter, best match first, with the positions of the matched characters.
+    // without a filter favourites come first, then the recent ones
+    fn arrange(
+        &self,
+        mut summaries: Vec<NamespaceSummary>,
+        remembered: &HostState,
+    ) -> Vec<(NamespaceSummary, Vec<usize>)> {
+        self.sort(&mut summaries);
+        if self.filter.is_empty() {
+            summaries.sort_by_key(|summary| {
+                let namespace = &summary.namespace;
+                if remembered.favourites.contains(namespace) {
+                    (0, 0)
+                } else {
+                    match remembered.recent.iter().position(|ns| ns == namespace) {
+                        Some(i) => (1, i),
+                        None => (2, 0),
+                    }
+                }
+            });
+        }
+        let mut matches = summaries
+            .into_iter()
+            .filter_map(|summary| {
+                let (score, positions) = fuzzy_match(&self.filter, &summary.namespace)?;
+                Some((score, summary, positions))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|(score, ..)| std::cmp::Reverse(*score)); // stable, so equal matches keep the column order
+        matches
+            .into_iter()
+            .map(|(_, summary, positions)| (summary, positions))
+            .collect()
+    }
+    fn scroll(&mut self, delta: isize) {
+        let current = self
+            .selected
+            .as_ref()
+            .and_then(|selected| self.shown.iter().position(|ns| ns == selected))
+            .unwrap_or(0);
+        let next = current
+            .saturating_add_signed(delta)
+            .min(self.shown.len().saturating_sub(1));
+        self.selected = self.shown.get(next).cloned();
+    }
+}
+
+impl Dashboard {
+    pub fn new(namespaces: Vec<String>, settings: &Settings) -> Dashboard {
+        Dashboard {
+            namespaces,
+            error: None,
+            connection: ConnectionState::Connected,
+            received_at: SystemTime::now(),
+            remembered: HostState::default(),
+            stale_after: settings.stale_after,
+            highlight_for: settings.highlight_for,
+            insecure: settings.connection.tls.insecure,
+            events: Vec::new(),
+            history: None,
+            pods: TableState::default().with_selected(Some(0)),
+            palette: Palette::from(settings.theme),
+            health: settings.health.clone(),
+            endpoint_link: settings
+                .endpoint_link
+                .clone()
+                .unwrap_or("https://ENDPOINT_PLACEHOLDER".to_string()), // .. :/.. Its been our case so far that everything is TLS, simpledash server has to return the protocol in use
+            picker: None,
+        }
+    }
+    // a new payload replaced the previous one, returns what changed
+    pub fn receive(
+        &mut self,
+        previous: &Payload,
+        payload: &Payload,
+        now: SystemTime,
+    ) -> &[diff::Event] {
+        self.received_at = now;
+        let changes = diff::diff(previous, payload, now);
+        let new = changes.len().min(MAX_EVENTS);
+        self.events.extend(changes);
+        let too_many = self.events.len().saturating_sub(MAX_EVENTS);
+        self.events.drain(..too_many);
+        &self.events[self.events.len() - new..]
+    }
+    pub fn toggle_history(&mut self) {
+        self.history = match (&self.history, &self.picker) {
+            (None, None) => Some(TableState::default().with_selected(Some(0))),
+            _ => None,
+        };
+    }
+    // the changes that are still highlighted
+    fn recent_events(&self, now: SystemTime) -> Vec<&diff::Event> {
+        self.events
+            .iter()
+            .filter(|event| now.duration_since(event.time).unwrap_or_default() < self.highlight_for)
+            .collect()
+    }
+    pub fn is_picking(&self) -> bool {
+        self.picker.is_some()
+    }
+    pub fn open_picker(&mut self) {
+        if self.picker.is_some() {
+            return;
+        }
+        let current = self.namespaces.first().cloned();
+        // globs can't be marked in the table, they have to be given with -n
+        let marked = match self.namespaces.len() {
+            0 | 1 => Vec::new(),
+            _ => self
+                .namespaces
+                .iter()
+                .filter(|ns| !glob::is_glob(ns))
+                .cloned()
+                .collect(),
+        };
+        self.picker = Some(NamespacePicker::new(current, marked));
+    }
+    // watches the marked namespaces or else the selected one, false if there is nothing to pick
+    pub fn pick(&mut self) -> bool {
+        let namespaces = match self.picker.as_ref() {
+            Some(picker) if !picker.marked.is_empty() => picker.marked.clone(),
+            Some(picker) => picker.selected.iter().cloned().collect(),
+            None => Vec::new(),
+        };
+        if namespaces.is_empty() {
+            return false;
+        }
+        self.watch(namespaces);
+        self.picker = None;
+        true
+    }
+    pub fn mark(&mut self) {
+        if let Some(picker) = self.picker.as_mut() {
+            if let Some(selected) = picker.selected.clone() {
+                match picker.marked.iter().position(|ns| *ns == selected) {
+                    Some(i) => {
+                        picker.marked.remove(i);
+                    }
+                    None => picker.marked.push(selected),
+                }
+            }
+        }
+    }
+    // clears the filter or goes back to the dashboard, false if there is nothing to go back to
+    pub fn back(&mut self) -> bool {
+        if self.history.take().is_some() {
+            return true;
+        }
+        match self.picker.as_mut() {
+            Some(picker) if !picker.filter.is_empty() => picker.filter.clear(),
+            Some(_) if self.namespaces.is_empty() => return false,
+            _ => self.picker = None,
+        }
+        true
+    }
+    // the selected namespace in the picker, otherwise the one on the dashboard if it shows just one
+    pub fn highlighted_namespace(&self) -> Option<String> {
+        match (&self.picker, self.namespaces.as_slice()) {
+            (Some(picker), _) => picker.selected.clone(),
+            (None, [namespace]) if !glob::is_glob(namespace) => Some(namespace.clone()),
+            (None, _) => None,
+        }
+    }
+    pub fn type_filter(&mut self, c: char) {
+        if let Some(picker) = self.picker.as_mut() {
+            picker.filter.push(c);
+            picker.selected = None; // the best match gets selected on the next draw
+        }
+    }
+    pub fn erase_filter(&mut self) {
+        if let Some(picker) = self.picker.as_mut() {
+            picker.filter.pop();
+            picker.selected = None;
+        }
+    }
+    pub fn sort(&mut self, delta: isize) {
+        if let Some(picker) = self.picker.as_mut() {
+            let current = SORT_COLUMNS
+                .iter()
+                .position(|c| *c == picker.sort)
+                .unwrap_or(0);
+            let next = (current as isize + delta).rem_euclid(SORT_COLUMNS.len() as isize);
+            picker.sort = SORT_COLUMNS[next as usize];
+            // names read best a to z, counts with the biggest first
+            picker.descending = picker.sort != SortBy::Namespace;
+        }
+    }
+    pub fn reverse_sort(&mut self) {
+        if let Some(picker) = self.picker.as_mut() {
+            picker.descending = !picker.descending;
+        }
+    }
+    pub fn watch(&mut self, namespaces: Vec<String>) {
+        self.namespaces = namespaces;
+        self.pods.select(Some(0));
+    }
+    // more than one namespace, so everything is grouped per namespace
+    fn is_watching_many(&self) -> bool {
+        self.namespaces.len() > 1 || self.namespaces.iter().any(|ns| glob::is_glob(ns))
+    }
+    pub fn scroll(&mut self, delta: isize) {
+        if let Some(picker) = self.picker.as_mut() {
+            picker.scroll(delta);
+            return;
+        }
+        if let Some(history) = self.history.as_mut() {
+            let selected = history.selected().unwrap_or(0);
+            history.select(Some(selected.saturating_add_signed(delta)));
+            return;
+        }
+        let selected = self.pods.selected().unwrap_or(0);
+        self.pods
+            .select(Some(selected.saturating_add_signed(delta))); // clamped to the number of rows when drawn
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Palette {
+    title: Color,
+    link: Color,
+    timestamp: Color,
+    hint: Color,
+    healthy: Color,
+    progressing: Color,
+    failing: Color,
+}
+
+impl From<Theme> for Palette {
+    fn from(theme: Theme) -> Palette {
+        match theme {
+            Theme::Dark => Palette {
+                title: Color::Magenta,
+                link: Color::Blue,
+                timestamp: Color::Yellow,
+                hint: Color::DarkGray,
+                healthy: Color::Green,
+                progressing: Color::Yellow,
+                failing: Color::Red,
+            },
+            // yellow and gray are hard to read on a light background
+            Theme::Light => Palette {
+                title: Color::Magenta,
+                link: Color::Blue,
+                timestamp: Color::Black,
+                hint: Color::Gray,
+                healthy: Color::Green,
+                progressing: Color::Rgb(175, 95, 0),
+                failing: Color::Red,
+            },
+            Theme::Mono => Palette {
+                title: Color::Reset,
+                link: Color::Reset,
+                timestamp: Color::Reset,
+                hint: Color::Reset,
+                healthy: Color::Reset,
+                progressing: Color::Reset,
+                failing: Color::Reset,
+            },
+        }
+    }
+}
+
+// a clickable piece of text, written on top of the ratatui buffer after each draw
+struct Link {
+    x: u16,
+    y: u16,
+    url: String,
+    text: String,
+}
+
+pub struct Tui {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+}
+
+impl Tui {
+    pub fn enter() -> io::Result<Tui> {
+        // restore the terminal before printing the panic message, otherwise it ends up on the alternate screen
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let _ = restore_terminal();
+            default_hook(info);
+        }));
+        let mut tui = Tui {
+            terminal: Terminal::new(CrosstermBackend::new(io::stdout()))?,
+        };
+        tui.resume()?;
+        Ok(tui)
+    }
+    pub fn suspend(&mut self) -> io::Result<()> {
+        restore_terminal()
+    }
+    pub fn resume(&mut self) -> io::Result<()> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        self.terminal.clear() // forces a full redraw on the next draw
+    }
+    pub fn draw(
+        &mut self,
+        payload: &Payload,
+        cluster_info: &ClusterInfo,
+        dashboard: &mut Dashboard,
+    ) -> io::Result<()> {
+        let mut links = Vec::new();
+        self.terminal.draw(|frame| {
+            if dashboard.is_picking() {
+                draw_namespace_picker(frame, payload, cluster_info, dashboard)
+            } else if dashboard.history.is_some() {
+                draw_history(frame, payload, cluster_info, dashboard)
+            } else {
+                draw_dashboard(frame, payload, cluster_info, dashboard, &mut links)
+            }
+        })?;
+        let stdout = self.terminal.backend_mut();
+        for link in links {
+            queue!(
+                stdout,
+                cursor::MoveTo(link.x, link.y),
+                Print(make_link(link.url, link.text))
+            )?;
+        }
+        stdout.flush()
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        let _ = restore_terminal();
+    }
+}
+
+fn restore_terminal() -> io::Result<()> {
+    terminal::disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, cursor::Show)
+}
+
+// while typing all characters go to the namespace filter instead of being shortcuts
+pub fn poll_input(timeout: Duration, typing: bool) -> Option<Input> {
+    if !event::poll(timeout).unwrap_or(false) {
+        return None;
+    }
+    match event::read().ok()? {
+        Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) => key_input(code, modifiers, typing),
+        Event::Resize(_, _) => Some(Input::Resize),
+        _ => None,
+    }
+}
+
+fn key_input(code: KeyCode, modifiers: KeyModifiers, typing: bool) -> Option<Input> {
+    match code {
+        KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => Some(Input::Quit), // no SIGINT in raw mode
+        KeyCode::Char('f') if modifiers.contains(KeyModifiers::CONTROL) => {
+            Some(Input::ToggleFavourite)
+        }
+        KeyCode::Char(' ') if typing => Some(Input::Mark), // namespaces can't contain spaces
+        KeyCode::Char(c) if typing => Some(Input::Type(c)),
+        KeyCode::Backspace if typing => Some(Input::Erase),
+        KeyCode::Char('n') => Some(Input::SwitchNamespace),
+        KeyCode::Char('f') => Some(Input::ToggleFavourite),
+        KeyCode::Char('e') => Some(Input::ToggleHistory),
+        KeyCode::Char('q') => Some(Input::Quit),
+        KeyCode::Up | KeyCode::Char('k') => Some(Input::Scroll(-1)),
+        KeyCode::Down | KeyCode::Char('j') => Some(Input::Scroll(1)),
+        KeyCode::PageUp => Some(Input::Scroll(-10)),
+        KeyCode::PageDown => Some(Input::Scroll(10)),
+        KeyCode::Enter => Some(Input::Select),
+        KeyCode::Esc => Some(Input::Back),
+        KeyCode::Left => Some(Input::Sort(-1)),
+        KeyCode::Right => Some(Input::Sort(1)),
+        KeyCode::Tab => Some(Input::ReverseSort),
+        _ => None,
+    }
+}
+
+fn draw_dashboard(
+    frame: &mut Frame,
+    payload: &Payload,
+    cluster_info: &ClusterInfo,
+    dashboard: &mut Dashboard,
+    links: &mut Vec<Link>,
+) {
+    let palette = dashboard.palette;
+    let namespaces = resolve_namespaces(&dashboard.namespaces, &cluster_info.namespaces);
+    let grouped = dashboard.is_watching_many();
+    let recent = dashboard.recent_events(SystemTime::now());
+    let endpoints = group_by_namespace(&namespaces, grouped, palette, |namespace| {
+        endpoint_lines(payload, namespace, &dashboard.endpoint_link, palette)
+    });
+    let deployments = group_by_namespace(&namespaces, grouped, palette, |namespace| {
+        deployment_lines(payload, namespace, cluster_info, &recent, palette)
+    });
+    let (pods, no_pods) = pod_rows(
+        payload,
+        &namespaces,
+        grouped,
+        &recent,
+        &dashboard.health,
+        palette,
+    );
+    let events = event_lines(&dashboard.events, &namespaces, grouped, palette);
+
+    // endpoints and deployments get what they need up to a quarter of the screen each, pods get the rest.
+    // the events pane only shows up once something changed
+    let max_pane_height = (frame.area().height / 4).max(3);
+    let events_height = match events.is_empty() {
+        true => 0,
+        false => (events.len() as u16 + 2).min(max_pane_height),
+    };
+    let [endpoints_area, deployments_area, pods_area, events_area, status_area] =
+        Layout::vertical([
+            Constraint::Length((endpoints.len() as u16 + 2).min(max_pane_height)),
+            Constraint::Length((deployments.len() as u16 + 2).min(max_pane_height)),
+            Constraint::Min(3),
+            Constraint::Length(events_height),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+    collect_links(endpoints_area, &endpoints, links);
+    collect_links(deployments_area, &deployments, links);
+    frame.render_widget(
+        Paragraph::new(endpoints.into_iter().map(|l| l.line).collect::<Vec<_>>())
+            .block(pane("Endpoints", palette)),
+        endpoints_area,
+    );
+    frame.render_widget(
+        Paragraph::new(deployments.into_iter().map(|l| l.line).collect::<Vec<_>>())
+            .block(pane("Deployments", palette)),
+        deployments_area,
+    );
+
+    if no_pods == 0 {
+        dashboard.pods.select(None);
+    } else {
+        let selected = dashboard.pods.selected().unwrap_or(0).min(no_pods - 1);
+        dashboard.pods.select(Some(selected));
+    }
+    let mut header = vec!["node", "pod name", "status", "tag"];
+    let mut widths = vec![
+        Constraint::Fill(1),
+        Constraint::Fill(2),
+        Constraint::Length(18),
+        Constraint::Fill(1),
+    ];
+    if grouped {
+        header.insert(0, "namespace");
+        widths.insert(0, Constraint::Fill(1));
+    }
+    let pods_table = Table::new(pods, widths)
+        .header(Row::new(header).bold())
+        .block(pane(&format!("Pods ({})", no_pods), palette))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(pods_table, pods_area, &mut dashboard.pods);
+    frame.render_widget(
+        Paragraph::new(events).block(pane("Events", palette)),
+        events_area,
+    );
+
+    frame.render_widget(status_bar(payload, cluster_info, dashboard), status_area);
+}
+
+fn draw_namespace_picker(
+    frame: &mut Frame,
+    payload: &Payload,
+    cluster_info: &ClusterInfo,
+    dashboard: &mut Dashboard,
+) {
+    let palette = dashboard.palette;
+    let summaries = payload.namespace_summaries(&cluster_info.namespaces, &dashboard.health);
+    let [filter_area, table_area, status_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(3),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let Some(picker) = dashboard.picker.as_mut() else {
+        return;
+    };
+    let matches = picker.arrange(summaries, &dashboard.remembered);
+    picker.shown = matches.iter().map(|(s, _)| s.namespace.clone()).collect();
+    let selected = picker
+        .selected
+        .as_ref()
+        .and_then(|selected| picker.shown.iter().position(|ns| ns == selected))
+        .unwrap_or(0);
+    picker.selected = picker.shown.get(selected).cloned();
+    picker
+        .table
+        .select(picker.selected.as_ref().map(|_| selected));
+
+    let filter = match picker.filter.is_empty() {
+        true => Line::from(Span::from("type to filter namespaces").fg(palette.hint)),
+        false => Line::from(vec![
+            Span::from("filter: ").fg(palette.hint),
+            Span::from(picker.filter.clone()).bold(),
+            Span::from("█").fg(palette.hint),
+        ]),
+    };
+    frame.render_widget(Paragraph::new(filter), filter_area);
+
+    let mut header = vec![String::new()]; // the health badge
+    header.extend(
+        [
+            "namespace",
+            "pods",
+            "unhealthy",
+            "deployments ready",
+            "ingresses",
+        ]
+        .into_iter()
+        .zip(SORT_COLUMNS)
+        .map(|(title, column)| match column == picker.sort {
+            true if picker.descending => format!("{} ▼", title),
+            true => format!("{} ▲", title),
+            false => title.to_string(),
+        }),
+    );
+    let rows = matches.iter().map(|(summary, positions)| {
+        let unhealthy_color = match summary.unhealthy_pods {
+            0 => palette.healthy,
+            _ => palette.failing,
+        };
+        let deployments_color = match summary.ready_deployments == summary.deployments {
+            true => palette.healthy,
+            false => palette.progressing,
+        };
+        Row::new(vec![
+            health_badge(summary, palette),
+            namespace_line(summary, positions, &dashboard.remembered, palette),
+            Line::from(summary.pods.to_string()),
+            Line::from(Span::from(summary.unhealthy_pods.to_string()).fg(unhealthy_color)),
+            Line::from(
+                Span::from(format!(
+                    "{}/{}",
+                    summary.ready_deployments, summary.deployments
+                ))
+                .fg(deployments_color),
+            ),
+            Line::from(summary.ingresses.to_string()),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(1),
+            Constraint::Fill(3),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(Row::new(header).bold())
+    .block(pane(
+        &format!(
+            "Namespaces ({}/{})",
+            picker.shown.len(),
+            cluster_info.namespaces.len()
+        ),
+        palette,
+    ))
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(table, table_area, &mut picker.table);
+
+    frame.render_widget(status_bar(payload, cluster_info, dashboard), status_area);
+}
+
+// every change sc saw in this session, in all namespaces, newest first
+fn draw_history(
+    frame: &mut Frame,
+    payload: &Payload,
+    cluster_info: &ClusterInfo,
+    dashboard: &mut Dashboard,
+) {
+    let palette = dashboard.palette;
+    let [table_area, status_area] =
+        Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+    let rows = dashboard
+        .events
+        .iter()
+        .rev()
+        .map(|event| {
+            let time = chrono::DateTime::<chrono::Local>::from(event.time).format("%H:%M:%S");
+            Row::new(vec![
+                Line::from(Span::from(time.to_string()).fg(palette.hint)),
+                Line::from(Span::from(event.namespace.clone()).fg(palette.title)),
+                Line::from(event.change.to_string()),
+            ])
+        })
+        .collect::<Vec<_>>();
+    let title = format!("Events ({})", rows.len());
+    if let Some(history) = dashboard.history.as_mut() {
+        let selected = history.selected().unwrap_or(0);
+        history.select(match rows.len() {
+            0 => None,
+            no_rows => Some(selected.min(no_rows - 1)),
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(8),
+                Constraint::Fill(1),
+                Constraint::Fill(3),
+            ],
+        )
+        .header(Row::new(vec!["time", "namespace", "event"]).bold())
+        .block(pane(&title, palette))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, table_area, history);
+    }
+
+    frame.render_widget(status_bar(payload, cluster_info, dashboard), status_area);
+}
+
+// different symbols as well as colours, so that the mono theme can tell them apart
+fn health_badge(summary: &NamespaceSummary, palette: Palette) -> Line<'static> {
+    if summary.unhealthy_pods > 0 {
+        Line::from(Span::from("✖").fg(palette.failing))
+    } else if summary.ready_deployments < summary.deployments {
+        Line::from(Span::from("◐").fg(palette.progressing))
+    } else {
+        Line::from(Span::from("✔").fg(palette.healthy))
+    }
+}
+
+// the namespace with the characters matching the filter highlighted
+fn namespace_line(
+    summary: &NamespaceSummary,
+    positions: &[usize],
+    remembered: &HostState,
+    palette: Palette,
+) -> Line<'static> {
+    let mut spans = Vec::new();
+    if remembered.favourites.contains(&summary.namespace) {
+        spans.push(Span::from("★ ").fg(palette.progressing));
+    }
+    spans.extend(summary.namespace.chars().enumerate().map(|(i, c)| {
+        match positions.contains(&i) {
+            true => Span::from(c.to_string())
+                .fg(palette.title)
+                .bold()
+                .underlined(),
+            false => Span::from(c.to_string()),
+        }
+    }));
+    if remembered.recent.contains(&summary.namespace) {
+        spans.push(Span::from(" recent").fg(palette.hint));
+    }
+    Line::from(spans)
+}
+
+// case insensitive subsequence match, consecutive characters and word starts score higher
+fn fuzzy_match(query: &str, text: &str) -> Option<(i32, Vec<usize>)> {
+    let query = query.to_lowercase().chars().collect::<Vec<char>>();
+    let text = text.to_lowercase().chars().collect::<Vec<char>>();
+    let Some(&first) = query.first() else {
+        return Some((0, Vec::new()));
+    };
+    let is_word_start = |i: usize| i == 0 || matches!(text[i - 1], '-' | '_' | '.');
+    let mut best: Option<(i32, Vec<usize>)> = None;
+    // greedy from every occurrence of the first character, so that "api" prefers "my-api" over "a-p-i"
+    for start in (0..text.len()).filter(|&i| text[i] == first) {
+        let mut positions = vec![start];
+        for &c in query.iter().skip(1) {
+            let from = positions[positions.len() - 1] + 1;
+            match text[from..].iter().position(|&t| t == c) {
+                Some(offset) => positions.push(from + offset),
+                None => break,
+            }
+        }
+        if positions.len() < query.len() {
+            break; // later starts have even less text left to match in
+        }
+        let score = positions
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| {
+                let consecutive = i > 0 && positions[i - 1] + 1 == p;
+                1 + if consecutive { 5 } else { 0 } + if is_word_start(p) { 3 } else { 0 }
+            })
+            .sum::<i32>();
+        if best.as_ref().is_none_or(|(best, _)| score > *best) {
+            best = Some((score, positions));
+        }
+    }
+    best
+}
+
+fn pane(title: &str, palette: Palette) -> Block<'static> {
+    Block::default()
+        .borders(Borders::ALL)
+        .title(Span::from(title.to_string()).fg(palette.title).bold())
+}
+
+fn status_bar<'a>(
+    payload: &'a Payload,
+    cluster_info: &'a ClusterInfo,
+    dashboard: &'a Dashboard,
+) -> Paragraph<'a> {
+    let palette = dashboard.palette;
+    let connection = match dashboard.connection {
+        ConnectionState::Connected => Span::from("● connected").fg(palette.healthy),
+        ConnectionState::Reconnecting { .. } => {
+            Span::from(format!("○ {}", dashboard.connection)).fg(palette.progressing)
+        }
+        ConnectionState::GaveUp { .. } => {
+            Span::from(format!("○ {}", dashboard.connection)).fg(palette.failing)
+        }
+        ConnectionState::Replaying { .. } => {
+            Span::from(format!("▶ {}", dashboard.connection)).fg(palette.healthy)
+        }
+        ConnectionState::ReplayFinished { .. } => {
+            Span::from(format!("■ {}", dashboard.connection)).fg(palette.hint)
+        }
+    };
+    let mut spans = vec![connection, Span::from("  ")];
+    if dashboard.insecure {
+        spans.push(
+            Span::from("⚠ insecure")
+                .fg(palette.failing)
+                .bold()
+                .reversed(),
+        );
+        spans.push(Span::from("  "));
+    }
+    spans.extend([
+        Span::from(match dashboard.is_picking() {
+            true => "all namespaces".to_string(),
+            false => dashboard.namespaces.join(", "),
+        })
+        .fg(palette.title)
+        .bold(),
+        Span::from(" in "),
+        Span::from(cluster_info.cluster_name.as_str())
+            .fg(palette.title)
+            .bold(),
+        Span::from(" as per "),
+        Span::from(payload.timestamp.as_str())
+            .fg(palette.timestamp)
+            .bold(),
+        Span::from(" "),
+    ]);
+    // a replay is as old as it was when it was recorded
+    let now = match dashboard.connection {
+        ConnectionState::Replaying { recorded_at, .. }
+        | ConnectionState::ReplayFinished { recorded_at } => recorded_at,
+        _ => SystemTime::now(),
+    };
+    let age = payload.age(dashboard.received_at, now);
+    if age > dashboard.stale_after {
+        spans.push(
+            Span::from(format!("(stale, {} old)", format_age(age)))
+                .fg(palette.failing)
+                .bold()
+                .reversed(),
+        );
+    } else {
+        spans.push(Span::from(format!("({} old)", format_age(age))).fg(palette.hint));
+    }
+    spans.push(Span::from("  "));
+    match &dashboard.error {
+        Some(error) => spans.push(Span::from(error.as_str()).fg(palette.failing).bold()),
+        None if dashboard.is_picking() => spans.push(
+            Span::from(
+                "↑↓ select | space: mark | enter: open | ←→ sort | tab: reverse | ctrl+f: favourite | esc: back",
+            )
+            .fg(palette.hint),
+        ),
+        None if dashboard.history.is_some() => {
+            spans.push(Span::from("↑↓ scroll | e/esc: back | q: quit").fg(palette.hint))
+        }
+        None => spans.push(
+            Span::from("↑↓ scroll | n: switch namespace | f: favourite | e: events | q: quit")
+                .fg(palette.hint),
+        ),
+    }
+    Paragraph::new(Line::from(spans))
+}
+
+fn format_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+// a line in a pane, optionally with a link to overlay on top of the text at the given column
+struct LinkedLine {
+    line: Line<'static>,
+    link: Option<(u16, String, String)>,
+}
+
+fn collect_links(area: Rect, lines: &[LinkedLine], links: &mut Vec<Link>) {
+    let inner = Block::default().borders(Borders::ALL).inner(area);
+    for (i, line) in lines.iter().enumerate().take(inner.height as usize) {
+        if let Some((column, url, text)) = &line.link {
+            // skip links that would be cut off by the pane border
+            if column + text.chars().count() as u16 <= inner.width {
+                links.push(Link {
+                    x: inner.x + column,
+                    y: inner.y + i as u16,
+                    url: url.clone(),
+                    text: text.clone(),
+                });
+            }
+        }
+    }
+}
+
+// globs are expanded to the namespaces they match, plain namespaces are kept even if the cluster doesn't list them
+fn resolve_namespaces(watched: &[String], cluster_namespaces: &[String]) -> Vec<String> {
+    let mut namespaces: Vec<String> = Vec::new();
+    for pattern in watched {
+        let mut matches = match glob::is_glob(pattern) {
+            true => cluster_namespaces
+                .iter()
+                .filter(|ns| glob::glob_match(pattern, ns))
+                .cloned()
+                .collect(),
+            false => vec![pattern.clone()],
+        };
+        matches.sort();
+        matches.retain(|ns| !namespaces.contains(ns));
+        namespaces.extend(matches);
+    }
+    namespaces
+}
+
+// the lines of each namespace under a heading with its name, indented, when watching many
+fn group_by_namespace(
+    namespaces: &[String],
+    grouped: bool,
+    palette: Palette,
+    lines: impl Fn(&str) -> Vec<LinkedLine>,
+) -> Vec<LinkedLine> {
+    let mut result = Vec::new();
+    for namespace in namespaces {
+        let lines = lines(namespace);
+        if !grouped {
+            result.extend(lines);
+            continue;
+        }
+        if lines.is_empty() {
+            continue;
+        }
+        result.push(LinkedLine {
+            line: Line::from(Span::from(namespace.clone()).fg(palette.title).bold()),
+            link: None,
+        });
+        result.extend(lines.into_iter().map(|mut line| {
+            line.line.spans.insert(0, Span::from("  "));
+            line.link = line.link.map(|(column, url, text)| (column + 2, url, text));
+            line
+        }));
+    }
+    result
+}
+
+fn endpoint_lines(
+    payload: &Payload,
+    namespace: &str,
+    endpoint_link: &str,
+    palette: Palette,
+) -> Vec<LinkedLine> {
+    let mut result = Vec::new();
+    if let Some(ingresses) = &payload.ingresses {
+        for ingress in ingresses {
+            if ingress.namespace != *namespace {
+                continue;
+            }
+            let url = endpoint_link.replace("ENDPOINT_PLACEHOLDER", &ingress.endpoint);
+            result.push(LinkedLine {
+                line: Line::from(vec![
+                    Span::from(url.clone()).fg(palette.link).bold(),
+                    Span::from(format!(" ({})", ingress.ip)),
+                ]),
+                link: Some((0, url.clone(), url)),
+            });
+        }
+    }
+    result
+}
+
+fn deployment_lines(
+    payload: &Payload,
+    namespace: &str,
+    cluster_info: &ClusterInfo,
+    recent: &[&diff::Event],
+    palette: Palette,
+) -> Vec<LinkedLine> {
+    let mut result = Vec::new();
+    for deployment in payload.deployments.iter() {
+        if deployment.namespace != *namespace {
+            continue;
+        }
+        // added after the link so that its column stays the same
+        let changed = recent.iter().rev().find_map(|event| match &event.change {
+            Change::ReplicasChanged {
+                deployment: name,
+                from,
+                ..
+            } if event.namespace == *namespace && *name == deployment.name => Some(
+                Span::from(format!(" (was {}/{})", from.0, from.1))
+                    .fg(palette.progressing)
+                    .bold(),
+            ),
+            _ => None,
+        });
+        let replicas = format!(
+            "{} ({}/{})",
+            deployment.name, deployment.ready_replicas, deployment.replicas
+        );
+        if cluster_info.deployment_logs_link_enabled {
+            let link_url = cluster_info
+                .deployment_logs_link
+                .replace("DEPLOYMENT_NAME_PLACEHOLDER", &deployment.name)
+                .replace("DEPLOYMENT_NAMESPACE_PLACEHOLDER", &deployment.namespace);
+            let column = replicas.chars().count() as u16 + 1;
+            let mut spans = vec![
+                Span::from(replicas),
+                Span::from(" "),
+                Span::from("view logs").fg(palette.link).bold(),
+            ];
+            spans.extend(changed);
+            result.push(LinkedLine {
+                line: Line::from(spans),
+                link: Some((column, link_url, "view logs".to_string())),
+            });
+        } else {
+            let mut spans = vec![Span::from(replicas)];
+            spans.extend(changed);
+            result.push(LinkedLine {
+                line: Line::from(spans),
+                link: None,
+            });
+        }
+    }
+    result
+}
+
+// grouped by namespace (in the order they are watched), then by node. recently added and changed
+// pods are marked with + and ~, removed ones stay crossed out with a - for a while
+fn pod_rows(
+    payload: &Payload,
+    namespaces: &[String],
+    grouped: bool,
+    recent: &[&diff::Event],
+    health: &HealthRules,
+    palette: Palette,
+) -> (Vec<Row<'static>>, usize) {
+    let change = |pod: &Pods| {
+        recent.iter().rev().find_map(|event| match &event.change {
+            change @ (Change::PodAdded { pod: changed, .. }
+            | Change::PodRemoved { pod: changed, .. }
+            | Change::PodStatusChanged { pod: changed, .. })
+                if changed.namespace == pod.namespace && changed.name == pod.name =>
+            {
+                Some(change)
+            }
+            _ => None,
+        })
+    };
+    let mut pods: Vec<(usize, &String, &Pods, Option<&Change>)> = payload
+        .nodes
+        .iter()
+        .flat_map(|(node, pods)| pods.iter().map(move |pod| (node, pod)))
+        .filter_map(|(node, pod)| {
+            let group = namespaces.iter().position(|ns| *ns == pod.namespace)?;
+            Some((group, node, pod, change(pod)))
+        })
+        .collect();
+    let removed = recent.iter().filter_map(|event| match &event.change {
+        Change::PodRemoved { node, pod } => Some((node, pod)),
+        _ => None,
+    });
+    for (node, pod) in removed {
+        let Some(group) = namespaces.iter().position(|ns| *ns == pod.namespace) else {
+            continue;
+        };
+        // a pod that came back since is already in the table
+        if let Some(change @ Change::PodRemoved { .. }) = change(pod) {
+            pods.push((group, node, pod, Some(change)));
+        }
+    }
+    pods.sort_by(|a, b| (a.0, a.1, &a.2.name).cmp(&(b.0, b.1, &b.2.name)));
+    pods.dedup_by(|a, b| (a.0, a.1, &a.2.name) == (b.0, b.1, &b.2.name));
+    let mut rows = Vec::new();
+    for (_, node, pod, change) in pods {
+        let status_color = match health.health(pod) {
+            Some(Health::Healthy) => palette.healthy,
+            Some(Health::Progressing) => palette.progressing,
+            Some(Health::Failing) => palette.failing,
+            None => palette.hint, // ignored by the health rules
+        };
+
+        let pod_image_tag = pod.image.split(':').next_back().unwrap_or("unknown");
+        let marker = match change {
+            Some(Change::PodAdded { .. }) => "+ ",
+            Some(Change::PodStatusChanged { .. }) => "~ ",
+            Some(Change::PodRemoved { .. }) => "- ",
+            _ => "",
+        };
+
+        let mut cells = vec![
+            Line::from(node.clone()),
+            Line::from(format!("{}{}", marker, pod.name)),
+            Line::from(Span::from(pod.status.to_string()).fg(status_color)).right_aligned(),
+            Line::from(pod_image_tag.to_string()),
+        ];
+        if grouped {
+            cells.insert(0, Line::from(pod.namespace.clone()));
+        }
+        rows.push(Row::new(cells).style(match change {
+            Some(Change::PodRemoved { .. }) => Style::default().fg(palette.hint).crossed_out(),
+            Some(_) => Style::default().bold(),
+            None => Style::default(),
+        }));
+    }
+    let no_pods = rows.len();
+    (rows, no_pods)
+}
+
+// the changes in the watched namespaces, newest first
+fn event_lines(
+    events: &[diff::Event],
+    namespaces: &[String],
+    grouped: bool,
+    palette: Palette,
+) -> Vec<Line<'static>> {
+    events
+        .iter()
+        .rev()
+        .filter(|event| namespaces.contains(&event.namespace))
+        .map(|event| {
+            let time = chrono::DateTime::<chrono::Local>::from(event.time).format("%H:%M:%S");
+            let mut spans = vec![Span::from(format!("{} ", time)).fg(palette.hint)];
+            if grouped {
+                spans.push(Span::from(format!("{} ", event.namespace)).fg(palette.title));
+            }
+            spans.push(Span::from(event.change.to_string()));
+            Line::from(spans)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Deployment, Ingress, PodStatus};
+    use ratatui::backend::TestBackend;
+
+    fn dashboard(namespace: &str) -> Dashboard {
+        let settings = Settings {
+            stale_after: Duration::from_secs(60),
+            highlight_for: Duration::from_secs(10),
+            ..Default::default()
+        };
+        let namespaces = Some(namespace.to_string()).filter(|ns| !ns.is_empty());
+        Dashboard::new(namespaces.into_iter().collect(), &settings)
+    }
+
+    fn render(payload: &Payload, namespace: &str, cluster_info: &ClusterInfo) -> String {
+        render_screen(payload, cluster_info, &mut dashboard(namespace))
+    }
+
+    fn render_screen(
+        payload: &Payload,
+        cluster_info: &ClusterInfo,
+        dashboard: &mut Dashboard,
+    ) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal
+            .draw(|frame| match dashboard.is_picking() {
+                true => draw_namespace_picker(frame, payload, cluster_info, dashboard),
+                false => draw_dashboard(frame, payload, cluster_info, dashboard, &mut Vec::new()),
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    #[test]
+    #[allow(clippy::needless_update)]
+    fn dashboard_shows_only_deployments_in_selected_namespace() {
+        // Arrange
+        let payload = Payload {
+            deployments: vec![
+                Deployment {
+                    name: "deployment1".to_string(),
+                    namespace: "namespace1".to_string(),
+                    ready_replicas: 2,
+                    replicas: 3,
+                    ..Default::default()
+                },
+                Deployment {
+                    name: "deployment2".to_string(),
+                    namespace: "namespace2".to_string(),
+                    ready_replicas: 1,
+                    replicas: 1,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let cluster_info = ClusterInfo {
+            deployment_logs_link_enabled: false,
+            ..Default::default()
+        };
+
+        // Act
+        let screen = render(&payload, "namespace1", &cluster_info);
+
+        // Assert
+        // contains this
+        assert!(screen.contains("deployment1 (2/3)"));
+        // and not this...
+        assert!(!screen.contains("deployment2 (1/1)"));
+    }
+
+    #[test]
+    fn dashboard_shows_only_endpoints_in_selected_namespace() {
+        // Arrange
+        let payload = Payload {
+            ingresses: Some(vec![
+                Ingress {
+                    endpoint: "endpoint1".to_string(),
+                    namespace: "namespace1".to_string(),
+                    ip: "172.23.1.205".to_string(),
+                },
+                Ingress {
+                    endpoint: "endpoint2".to_string(),
+                    namespace: "namespace2".to_string(),
+                    ip: "172.23.1.205".to_string(),
+                },
+            ]),
+            ..Default::default()
+        };
+
+        // Act
+        let screen = render(&payload, "namespace1", &ClusterInfo::default());
+
+        // Assert
+        // contains this
+        assert!(screen.contains("https://endpoint1 (172.23.1.205)"));
+        // and not this
+        assert!(!screen.contains("endpoint2"));
+    }
+
+    #[test]
+    fn dashboard_shows_only_pods_in_selected_namespace() {
+        // Arrange
+        let payload = Payload {
+            nodes: std::collections::HashMap::from_iter(vec![
+                (
+                    "node1".to_string(),
+                    vec![
+                        Pods {
+                            namespace: "namespace1".to_string(),
+                            name: "pod1".to_string(),
+                            status: PodStatus::Running,
+                            image: "image1:tag1".to_string(),
+                        },
+                        Pods {
+                            namespace: "namespace2".to_string(),
+                            name: "pod2".to_string(),
+                            status: PodStatus::Running,
+                            image: "image2:tag2".to_string(),
+                        },
+                    ],
+                ),
+                (
+                    "node2".to_string(),
+                    vec![Pods {
+                        namespace: "namespace1".to_string(),
+                        name: "pod3".to_string(),
+                        status: PodStatus::Running,
+                        image: "image3:tag3".to_string(),
+                    }],
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        // Act
+        let (_, no_of_pods) = pod_rows(
+            &payload,
+            &["namespace1".to_string()],
+            false,
+            &[],
+            &HealthRules::default(),
+            Palette::from(Theme::Dark),
+        );
+        let screen = render(&payload, "namespace1", &ClusterInfo::default());
+
+        // Assert
+        assert!(no_of_pods == 2);
+        assert!(screen.contains("Pods (2)"));
+
+        // contains this (pod 1 and pod 3 in node 1 and node 2)
+        assert!(screen.contains("node1"));
+        assert!(screen.contains("pod1"));
+        assert!(screen.contains("Running"));
+        assert!(screen.contains("tag1"));
+        assert!(screen.contains("node2"));
+        assert!(screen.contains("pod3"));
+        assert!(screen.contains("tag3"));
+        // and not this (pod2)
+        assert!(!screen.contains("pod2"));
+        assert!(!screen.contains("tag2"));
+    }
+
+    #[test]
+    fn dashboard_status_bar_shows_cluster_name_and_timestamp() {
+        // Arrange
+        let payload = Payload {
+            timestamp: "2023-10-18 12:00:00".to_string(),
+            ..Default::default()
+        };
+        let cluster_info = ClusterInfo {
+            cluster_name: "cluster1".to_string(),
+            ..Default::default()
+        };
+
+        // Act
+        let screen = render(&payload, "namespace1", &cluster_info);
+
+        // Assert
+        assert!(screen.contains("namespace1 in cluster1 as per 2023-10-18 12:00:00"));
+    }
+    #[test]
+    fn status_bar_flags_stale_data() {
+        // Arrange
+        let fresh = Payload {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            ..Default::default()
+        };
+        let stale = Payload {
+            timestamp: "2023-10-18T12:00:00Z".to_string(),
+            ..Default::default()
+        };
+
+        // Act
+        let fresh_screen = render(&fresh, "namespace1", &ClusterInfo::default());
+        let stale_screen = render(&stale, "namespace1", &ClusterInfo::default());
+
+        // Assert
+        assert!(fresh_screen.contains("(0s old)"));
+        assert!(!fresh_screen.contains("stale"));
+        assert!(stale_screen.contains("(stale, "));
+    }
+    #[test]
+    fn namespace_picker_sorts_and_picks_the_selected_namespace() {
+        // Arrange
+        let pod = |namespace: &str, name: &str, status| Pods {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            image: "image:1.0".to_string(),
+            status,
+        };
+        let payload = Payload {
+            nodes: [(
+                "node1".to_string(),
+                vec![
+                    pod("alpha", "a-1", PodStatus::Running),
+                    pod("beta", "b-1", PodStatus::CrashLoopBackOff),
+                    pod("beta", "b-2", PodStatus::Running),
+                    pod("gamma", "g-1", PodStatus::Running),
+                    pod("gamma", "g-2", PodStatus::Running),
+                    pod("gamma", "g-3", PodStatus::Running),
+                ],
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let cluster_info = ClusterInfo {
+            namespaces: vec!["alpha".to_string(), "beta".to_string(), "gamma".to_string()],
+            ..Default::default()
+        };
+        let mut dashboard = dashboard("");
+        dashboard.open_picker();
+
+        // Act
+        let by_unhealthy = render_screen(&payload, &cluster_info, &mut dashboard);
+        dashboard.sort(-1); // pods
+        let by_pods = render_screen(&payload, &cluster_info, &mut dashboard);
+        dashboard.scroll(1); // the selection stays on beta when sorting, so this is alpha
+        render_screen(&payload, &cluster_info, &mut dashboard);
+        let picked = dashboard.pick();
+
+        // Assert
+        assert!(by_unhealthy.contains("unhealthy ▼"));
+        assert!(by_unhealthy.find("beta").unwrap() < by_unhealthy.find("alpha").unwrap());
+        assert!(by_pods.contains("pods ▼"));
+        assert!(by_pods.find("gamma").unwrap() < by_pods.find("beta").unwrap());
+        assert!(picked);
+        assert!(!dashboard.is_picking());
+        assert_eq!(dashboard.namespaces, vec!["alpha"]);
+    }
+    #[test]
+    fn fuzzy_match_prefers_consecutive_characters_and_word_starts() {
+        // Act
+        let exact = fuzzy_match("api", "payments-api");
+        let scattered = fuzzy_match("api", "a-p-i");
+        let missing = fuzzy_match("api", "payments");
+
+        // Assert
+        assert_eq!(exact.as_ref().unwrap().1, vec![9, 10, 11]);
+        assert!(exact.unwrap().0 > scattered.unwrap().0);
+        assert_eq!(missing, None);
+        assert_eq!(fuzzy_match("", "anything"), Some((0, Vec::new())));
+    }
+    #[test]
+    fn namespace_picker_filters_while_typing() {
+        // Arrange
+        let cluster_info = ClusterInfo {
+            namespaces: vec![
+                "payments".to_string(),
+                "payments-api".to_string(),
+                "monitoring".to_string(),
+            ],
+            ..Default::default()
+        };
+        let mut dashboard = dashboard("");
+        dashboard.open_picker();
+
+        // Act
+        "pay-api".chars().for_each(|c| dashboard.type_filter(c));
+        let screen = render_screen(&Payload::default(), &cluster_info, &mut dashboard);
+        let picked = dashboard.pick();
+
+        // Assert
+        assert!(screen.contains("filter: pay-api"));
+        assert!(screen.contains("Namespaces (1/3)"));
+        assert!(!screen.contains("monitoring"));
+        assert!(picked);
+        assert_eq!(dashboard.namespaces, vec!["payments-api"]);
+    }
+    #[test]
+    fn namespace_picker_lists_favourites_then_recent_namespaces_first() {
+        // Arrange
+        let cluster_info = ClusterInfo {
+            namespaces: ["alpha", "beta", "gamma", "delta"]
+                .map(String::from)
+                .to_vec(),
+            ..Default::default()
+        };
+        let mut dashboard = dashboard("");
+        dashboard.remembered = HostState {
+            recent: vec!["beta".to_string(), "alpha".to_string()],
+            favourites: vec!["gamma".to_string()],
+            ..Default::default()
+        };
+        dashboard.open_picker();
+
+        // Act
+        let screen = render_screen(&Payload::default(), &cluster_info, &mut dashboard);
+
+        // Assert
+        let position = |namespace: &str| screen.find(namespace).unwrap();
+        assert!(screen.contains("★ gamma"));
+        assert!(position("gamma") < position("beta"));
+        assert!(position("beta") < position("alpha"));
+        assert!(position("alpha") < position("delta"));
+    }
+    #[test]
+    fn n_switches_namespace_through_the_picker_and_back_to_the_dashboard() {
+        // Arrange
+        let pod = |namespace: &str, name: &str| Pods {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            image: "image:1.0".to_string(),
+            status: PodStatus::Running,
+        };
+        let payload = Payload {
+            nodes: [(
+                "node1".to_string(),
+                vec![pod("alpha", "alpha-pod"), pod("beta", "beta-pod")],
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let cluster_info = ClusterInfo {
+            namespaces: vec!["alpha".to_string(), "beta".to_string()],
+            ..Default::default()
+        };
+        let mut dashboard = dashboard("alpha");
+        let before = render_screen(&payload, &cluster_info, &mut dashboard);
+
+        // Act
+        let input = key_input(
+            KeyCode::Char('n'),
+            KeyModifiers::NONE,
+            dashboard.is_picking(),
+        );
+        dashboard.open_picker(); // what the dashboard loop does on SwitchNamespace
+        let picker = render_screen(&payload, &cluster_info, &mut dashboard);
+        let typed = key_input(
+            KeyCode::Char('n'),
+            KeyModifiers::NONE,
+            dashboard.is_picking(),
+        );
+        "beta".chars().for_each(|c| dashboard.type_filter(c));
+        render_screen(&payload, &cluster_info, &mut dashboard);
+        let picked = dashboard.pick();
+        let after = render_screen(&payload, &cluster_info, &mut dashboard);
+
+        // Assert
+        assert!(before.contains("alpha-pod") && !before.contains("beta-pod"));
+        assert!(matches!(input, Some(Input::SwitchNamespace)));
+        assert!(picker.contains("Namespaces (2/2)"));
+        assert!(matches!(typed, Some(Input::Type('n')))); // the filter gets the n while picking
+        assert!(picked);
+        assert_eq!(dashboard.namespaces, vec!["beta"]);
+        assert!(after.contains("beta-pod") && !after.contains("alpha-pod"));
+    }
+    #[test]
+    fn dashboard_groups_everything_per_namespace_when_watching_a_glob() {
+        // Arrange
+        let pod = |namespace: &str, name: &str| Pods {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            image: "image:1.0".to_string(),
+            status: PodStatus::Running,
+        };
+        let deployment = |namespace: &str, name: &str| Deployment {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            replicas: 1,
+            ready_replicas: 1,
+        };
+        let payload = Payload {
+            nodes: [(
+                "node1".to_string(),
+                vec![
+                    pod("team-b-api", "api-1"),
+                    pod("team-a-api", "api-2"),
+                    pod("team-c-api", "api-3"),
+                ],
+            )]
+            .into(),
+            deployments: vec![
+                deployment("team-b-api", "deployment-b"),
+                deployment("team-a-api", "deployment-a"),
+                deployment("team-c-api", "deployment-c"),
+            ],
+            ..Default::default()
+        };
+        let cluster_info = ClusterInfo {
+            namespaces: ["team-a-api", "team-b-api", "team-c-api"]
+                .map(String::from)
+                .to_vec(),
+            ..Default::default()
+        };
+        let mut dashboard = dashboard("");
+        dashboard.watch(vec!["team-b-*".to_string(), "team-a-*".to_string()]);
+
+        // Act
+        let screen = render_screen(&payload, &cluster_info, &mut dashboard);
+
+        // Assert
+        let position = |text: &str| screen.find(text).unwrap();
+        assert!(position("team-b-api") < position("  deployment-b"));
+        assert!(position("deployment-b") < position("team-a-api"));
+        assert!(position("team-a-api") < position("  deployment-a"));
+        assert!(!screen.contains("deployment-c"));
+        assert!(screen.contains("namespace"));
+        assert!(screen.contains("Pods (2)"));
+        assert!(!screen.contains("api-3"));
+    }
+    #[test]
+    fn dashboard_highlights_changes_since_the_previous_payload() {
+        // Arrange
+        let pod = |name: &str, status| Pods {
+            namespace: "namespace1".to_string(),
+            name: name.to_string(),
+            image: "image:1.0".to_string(),
+            status,
+        };
+        let deployment = |ready_replicas| Deployment {
+            namespace: "namespace1".to_string(),
+            name: "api".to_string(),
+            replicas: 2,
+            ready_replicas,
+        };
+        let previous = Payload {
+            nodes: [(
+                "node1".to_string(),
+                vec![
+                    pod("api-1", PodStatus::Pending),
+                    pod("api-2", PodStatus::Running),
+                    pod("worker-1", PodStatus::Running),
+                ],
+            )]
+            .into(),
+            deployments: vec![deployment(1)],
+            ..Default::default()
+        };
+        let payload = Payload {
+            nodes: [(
+                "node1".to_string(),
+                vec![
+                    pod("api-1", PodStatus::Running),
+                    pod("api-3", PodStatus::Pending),
+                    pod("worker-1", PodStatus::Running),
+                ],
+            )]
+            .into(),
+            deployments: vec![deployment(2)],
+            ..Default::default()
+        };
+        let mut dashboard = dashboard("namespace1");
+
+        // Act
+        dashboard.receive(&previous, &payload, SystemTime::now());
+        let screen = render_screen(&payload, &ClusterInfo::default(), &mut dashboard);
+        dashboard
+            .events
+            .iter_mut()
+            .for_each(|event| event.time -= Duration::from_secs(60));
+        let later = render_screen(&payload, &ClusterInfo::default(), &mut dashboard);
+
+        // Assert
+        assert!(screen.contains("~ api-1"));
+        assert!(screen.contains("- api-2"));
+        assert!(screen.contains("+ api-3"));
+        assert!(screen.contains(" worker-1"));
+        assert!(screen.contains("api (2/2) (was 1/2)"));
+        assert!(screen.contains("Events"));
+        assert!(screen.contains("pod api-1 Pending → Running"));
+        assert!(!later.contains("- api-2"));
+        assert!(!later.contains("~ api-1"));
+        assert!(!later.contains("(was 1/2)"));
+        assert!(later.contains("pod api-1 Pending → Running"));
+    }
+}